@@ -13,6 +13,7 @@ pub enum AxisBinding {
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ActionBinding {
   Interact,
+  ToggleWalk,
 }
 
 impl Display for AxisBinding {
@@ -0,0 +1,200 @@
+use crate::{
+    resources::terrain::{HeightGenerator, DEFAULT_SEED},
+    systems::terrain::TerrainSettings,
+    utils::{errors::GameError, terrain_preview::render_previews},
+};
+use amethyst::{config::Config, utils::application_root_dir};
+use std::{ops::Range, path::PathBuf};
+
+pub const USAGE: &str = "usage:
+    test-amethyst
+    test-amethyst terrain-preview [--seed <u32>] [--region <from..to>[,<from..to>]] [--out <file.png>]
+        [--raw <file.r16>]
+    test-amethyst generate-manifest [--out <file.ron>]";
+
+pub enum Command {
+    Game,
+    TerrainPreview(PreviewOptions),
+    GenerateManifest(Option<PathBuf>), // defaults to `assets/manifest.ron`
+}
+
+#[derive(Debug)]
+pub struct PreviewOptions {
+    pub seed: u32,
+    pub region_x: Range<i32>, // in chunks
+    pub region_y: Range<i32>,
+    pub out: PathBuf,
+    pub raw: Option<PathBuf>, // 16 bit heights, readable as a terrain heightmap
+}
+
+impl Default for PreviewOptions {
+    fn default() -> Self {
+        Self {
+            seed: DEFAULT_SEED,
+            region_x: -2..3,
+            region_y: -2..3,
+            out: PathBuf::from("terrain.png"),
+            raw: None,
+        }
+    }
+}
+
+pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Result<Command, String> {
+    match args.next().as_ref().map(String::as_str) {
+        None => Ok(Command::Game),
+        Some("terrain-preview") => parse_preview(args).map(Command::TerrainPreview),
+        Some("generate-manifest") => parse_manifest(args).map(Command::GenerateManifest),
+        Some(other) => Err(format!("unknown command `{}`", other)),
+    }
+}
+
+fn parse_preview<I: Iterator<Item = String>>(mut args: I) -> Result<PreviewOptions, String> {
+    let mut options = PreviewOptions::default();
+
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for `{}`", arg))?;
+        match arg.as_str() {
+            "--seed" => {
+                options.seed = value
+                    .parse()
+                    .map_err(|_| format!("invalid seed `{}`", value))?
+            }
+            "--region" => {
+                let mut ranges = value.split(',');
+                let x = parse_range(ranges.next().unwrap_or(""))?;
+                let y = match ranges.next() {
+                    Some(range) => parse_range(range)?,
+                    None => x.clone(),
+                };
+                options.region_x = x;
+                options.region_y = y;
+            }
+            "--out" => options.out = PathBuf::from(value),
+            "--raw" => options.raw = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown option `{}`", arg)),
+        }
+    }
+
+    Ok(options)
+}
+
+fn parse_manifest<I: Iterator<Item = String>>(mut args: I) -> Result<Option<PathBuf>, String> {
+    match (args.next(), args.next()) {
+        (None, _) => Ok(None),
+        (Some(ref arg), Some(value)) if arg == "--out" => Ok(Some(PathBuf::from(value))),
+        (Some(ref arg), None) if arg == "--out" => Err(format!("missing value for `{}`", arg)),
+        (Some(arg), _) => Err(format!("unknown option `{}`", arg)),
+    }
+}
+
+// `-5..5` is inclusive on both ends, like the chunk coordinates it describes
+fn parse_range(value: &str) -> Result<Range<i32>, String> {
+    let invalid = || format!("invalid region `{}`, expected `from..to`", value);
+    let mut bounds = value.splitn(2, "..");
+    let from = bounds
+        .next()
+        .and_then(|v| v.parse::<i32>().ok())
+        .ok_or_else(invalid)?;
+    let to = bounds
+        .next()
+        .and_then(|v| v.parse::<i32>().ok())
+        .ok_or_else(invalid)?;
+    if to < from {
+        return Err(invalid());
+    }
+    Ok(from..to + 1)
+}
+
+// generates the terrain maps without creating a window or touching the gpu,
+// using the same terrain settings as the game
+pub fn terrain_preview(options: &PreviewOptions) -> Result<(), GameError> {
+    log::info!("Rendering terrain preview {:?}", options);
+    let root = application_root_dir().map_err(|source| GameError::Io { path: None, source })?;
+    let settings_path = root.join("config").join("terrain.ron");
+    let settings = TerrainSettings::load(&settings_path);
+    let generator =
+        HeightGenerator::from_settings(options.seed, &settings, &root).map_err(|source| {
+            GameError::Terrain {
+                path: settings_path,
+                source,
+            }
+        })?;
+    render_previews(
+        &settings,
+        &generator,
+        options.region_x.clone(),
+        options.region_y.clone(),
+        &options.out,
+        options.raw.as_ref().map(PathBuf::as_path),
+    )
+    .map_err(|source| GameError::Terrain {
+        path: options.out.clone(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> impl Iterator<Item = String> + '_ {
+        line.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn no_arguments_run_the_game() {
+        match parse(args("")) {
+            Ok(Command::Game) => {}
+            _ => panic!("expected the game command"),
+        }
+    }
+
+    #[test]
+    fn preview_options() {
+        let options = parse_preview(args(
+            "--seed 7 --region -5..5,0..3 --out map.png --raw map.r16",
+        ))
+        .unwrap();
+        assert_eq!(options.seed, 7);
+        assert_eq!(options.region_x, -5..6);
+        assert_eq!(options.region_y, 0..4);
+        assert_eq!(options.out, PathBuf::from("map.png"));
+        assert_eq!(options.raw, Some(PathBuf::from("map.r16")));
+    }
+
+    #[test]
+    fn single_region_applies_to_both_axes() {
+        let options = parse_preview(args("--region -1..1")).unwrap();
+        assert_eq!(options.region_x, -1..2);
+        assert_eq!(options.region_y, -1..2);
+    }
+
+    #[test]
+    fn bad_region() {
+        for region in &["5..-5", "a..b", "3", "1..2,x"] {
+            let error = parse_preview(args(&format!("--region {}", region))).unwrap_err();
+            assert!(error.starts_with("invalid region"), "{}: {}", region, error);
+        }
+    }
+
+    #[test]
+    fn flag_without_value() {
+        assert_eq!(
+            parse_preview(args("--seed 7 --out")).unwrap_err(),
+            "missing value for `--out`"
+        );
+        assert_eq!(
+            parse(args("generate-manifest --out")).err().as_deref(),
+            Some("missing value for `--out`")
+        );
+    }
+
+    #[test]
+    fn unknown_command_and_option() {
+        assert!(parse(args("preview")).is_err());
+        assert!(parse_preview(args("--size 3")).is_err());
+        assert!(parse_preview(args("--seed x")).is_err());
+    }
+}
@@ -1,10 +1,18 @@
+use crate::components::{
+    physics::{Collider, RigidBody},
+    PrefabBase, PrefabVariant,
+};
 use amethyst::{
     assets::{PrefabData, ProgressCounter},
     core::{Named, Transform},
     derive::PrefabData,
     ecs::Entity,
     renderer::rendy::mesh::{Normal, Position, TexCoord},
-    renderer::{formats::GraphicsPrefab, rendy::mesh::MeshBuilder, shape::FromShape},
+    renderer::{
+        formats::{mesh::MeshPrefab, mtl::MaterialPrefab},
+        rendy::mesh::MeshBuilder,
+        shape::FromShape,
+    },
     Error,
 };
 use serde::{Deserialize, Serialize};
@@ -16,19 +24,88 @@ pub struct CritterPrefabData<V = (Vec<Position>, Vec<Normal>, Vec<TexCoord>)>
 where
     V: FromShape + Into<MeshBuilder<'static>>,
 {
+    base: Option<PrefabBase>,
     pub name: Option<Named>,
-    graphics: Option<GraphicsPrefab<V>>,
+    graphics: Option<CritterGraphics<V>>,
     transform: Option<Transform>,
+    collider: Option<Collider>,
+    rigid_body: Option<RigidBody>,
+}
+
+named_prefab!(CritterPrefabData);
+
+impl<V> PrefabVariant for CritterPrefabData<V>
+where
+    V: FromShape + Into<MeshBuilder<'static>>,
+{
+    fn base(&self) -> Option<&str> {
+        self.base.as_ref().map(|base| base.0.as_str())
+    }
+
+    fn extend(self, base: Self) -> Self {
+        let graphics = match (self.graphics, base.graphics) {
+            (Some(graphics), Some(base)) => Some(graphics.extend(base)),
+            (graphics, base) => graphics.or(base),
+        };
+        Self {
+            base: None,
+            // the name isn't inherited, it would clash with the base's alias
+            name: self.name,
+            graphics,
+            transform: self.transform.or(base.transform),
+            collider: self.collider.or(base.collider),
+            rigid_body: self.rigid_body.or(base.rigid_body),
+        }
+    }
+}
+
+// like `GraphicsPrefab`, but a variant can leave out either part
+#[derive(Deserialize, Debug, Serialize, PrefabData)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct CritterGraphics<V>
+where
+    V: FromShape + Into<MeshBuilder<'static>>,
+{
+    mesh: Option<MeshPrefab<V>>,
+    material: Option<MaterialPrefab>,
+}
+
+impl<V> Default for CritterGraphics<V>
+where
+    V: FromShape + Into<MeshBuilder<'static>>,
+{
+    fn default() -> Self {
+        Self {
+            mesh: None,
+            material: None,
+        }
+    }
 }
 
-impl<T> crate::components::NamedPrefab for CritterPrefabData<T>
+impl<V> CritterGraphics<V>
 where
-    T: FromShape + Into<MeshBuilder<'static>>,
+    V: FromShape + Into<MeshBuilder<'static>>,
 {
-    fn name(&self) -> Option<&str> {
-        if let Some(name) = &self.name {
-            return Some(&name.name);
+    fn extend(self, base: Self) -> Self {
+        let material = match (self.material, base.material) {
+            (Some(material), Some(base)) => Some(extend_material(material, base)),
+            (material, base) => material.or(base),
+        };
+        Self {
+            mesh: self.mesh.or(base.mesh),
+            material,
         }
-        None
     }
 }
+
+// texture maps are overridden one by one, the other settings come from the base
+fn extend_material(material: MaterialPrefab, mut base: MaterialPrefab) -> MaterialPrefab {
+    base.albedo = material.albedo.or(base.albedo);
+    base.emission = material.emission.or(base.emission);
+    base.normal = material.normal.or(base.normal);
+    base.metallic_roughness = material.metallic_roughness.or(base.metallic_roughness);
+    base.ambient_occlusion = material.ambient_occlusion.or(base.ambient_occlusion);
+    base.cavity = material.cavity.or(base.cavity);
+    base
+}
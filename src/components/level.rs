@@ -1,3 +1,4 @@
+use crate::components::physics::{Collider, RigidBody};
 use amethyst::{
     assets::{PrefabData, ProgressCounter},
     controls::ControlTagPrefab,
@@ -29,16 +30,8 @@ where
     control_tag: Option<ControlTagPrefab>,
     auto_fov: Option<AutoFov>,
     removal: Option<Removal<()>>,
+    collider: Option<Collider>,
+    rigid_body: Option<RigidBody>,
 }
 
-impl<T> crate::components::NamedPrefab for LevelPrefabData<T>
-where
-    T: FromShape + Into<MeshBuilder<'static>>,
-{
-    fn name(&self) -> Option<&str> {
-        if let Some(name) = &self.name {
-            return Some(&name.name);
-        }
-        None
-    }
-}
+named_prefab!(LevelPrefabData);
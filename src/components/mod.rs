@@ -1,7 +1,57 @@
+// implements `NamedPrefab` for a prefab data type generic over its vertex type,
+// the type needs a `name: Option<Named>` field
+macro_rules! named_prefab {
+    ($prefab:ident) => {
+        impl<V> crate::components::NamedPrefab for $prefab<V>
+        where
+            V: amethyst::renderer::shape::FromShape
+                + Into<amethyst::renderer::rendy::mesh::MeshBuilder<'static>>,
+        {
+            fn name(&self) -> Option<&str> {
+                self.name.as_ref().map(|named| named.name.as_ref())
+            }
+        }
+    };
+}
+
 pub mod critter;
 pub mod level;
+pub mod physics;
+pub mod prop;
 pub mod terrain;
+pub mod walker;
+
+use amethyst::{assets::PrefabData, ecs::Entity, Error};
+use serde::{Deserialize, Serialize};
 
 pub trait NamedPrefab {
     fn name(&self) -> Option<&str>;
 }
+
+// prefab data that can extend another prefab of its kind, see `resources::prefab_variants`
+pub trait PrefabVariant: Sized {
+    // id or name of the extended prefab
+    fn base(&self) -> Option<&str>;
+    // fills in whatever this variant leaves out from the base
+    fn extend(self, base: Self) -> Self;
+}
+
+// the `base` field of a variant, it is resolved before loading and adds nothing to the entity
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct PrefabBase(pub String);
+
+impl<'a> PrefabData<'a> for PrefabBase {
+    type SystemData = ();
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        _: Entity,
+        _: &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
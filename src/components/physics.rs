@@ -0,0 +1,154 @@
+use amethyst::{
+    assets::PrefabData,
+    core::math::Vector3,
+    derive::PrefabData,
+    ecs::{Component, DenseVecStorage, Entity, WriteStorage},
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub enum ColliderShape {
+    Ball { radius: f32 },
+    Cuboid { half_extents: (f32, f32, f32) },
+    Capsule { half_height: f32, radius: f32 }, // upright, half_height excludes the caps
+}
+
+impl ColliderShape {
+    // distance from the center to the lowest point, rotation is ignored
+    pub fn half_height(&self) -> f32 {
+        match *self {
+            ColliderShape::Ball { radius } => radius,
+            ColliderShape::Cuboid { half_extents } => half_extents.1,
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+            } => half_height + radius,
+        }
+    }
+
+    // radius of a sphere around the center enclosing the whole shape
+    pub fn bounding_radius(&self) -> f32 {
+        match *self {
+            ColliderShape::Ball { radius } => radius,
+            ColliderShape::Cuboid {
+                half_extents: (x, y, z),
+            } => (x * x + y * y + z * z).sqrt(),
+            ColliderShape::Capsule {
+                half_height,
+                radius,
+            } => half_height + radius,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+#[serde(deny_unknown_fields)]
+pub struct Collider {
+    pub shape: ColliderShape,
+    #[serde(default)]
+    pub restitution: f32, // 0 stops on impact, 1 bounces back at full speed
+    #[serde(default = "default_friction")]
+    pub friction: f32,
+}
+
+impl Component for Collider {
+    type Storage = DenseVecStorage<Self>;
+}
+
+fn default_friction() -> f32 {
+    0.5
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum BodyKind {
+    Dynamic,   // moved by gravity and collisions
+    Kinematic, // moved by other systems, pushes dynamic bodies
+    Static,
+}
+
+impl Default for BodyKind {
+    fn default() -> Self {
+        BodyKind::Dynamic
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PrefabData)]
+#[prefab(Component)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct RigidBody {
+    pub kind: BodyKind,
+    pub mass: f32,
+    pub gravity_scale: f32,
+    pub linear_damping: f32,
+    #[serde(skip)]
+    pub velocity: Vector3<f32>,
+}
+
+impl Component for RigidBody {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self {
+            kind: BodyKind::Dynamic,
+            mass: 1.,
+            gravity_scale: 1.,
+            linear_damping: 0.1,
+            velocity: Vector3::zeros(),
+        }
+    }
+}
+
+impl RigidBody {
+    pub fn inverse_mass(&self) -> f32 {
+        match self.kind {
+            BodyKind::Dynamic if self.mass > 0. => 1. / self.mass,
+            _ => 0.,
+        }
+    }
+}
+
+// terrain collider of a chunk, one height per voxel center
+#[derive(Debug)]
+pub struct Heightfield {
+    pub origin: (f32, f32), // world position of the first voxel center
+    pub cell_size: f32,
+    pub size: usize, // cells per side
+    pub heights: Vec<f32>,
+}
+
+impl Component for Heightfield {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Heightfield {
+    // bilinearly interpolated height, positions outside are clamped to the edge
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let last = (self.size - 1) as f32;
+        let gx = ((x - self.origin.0) / self.cell_size).max(0.).min(last);
+        let gz = ((z - self.origin.1) / self.cell_size).max(0.).min(last);
+        let (x0, z0) = (gx.floor() as usize, gz.floor() as usize);
+        let (x1, z1) = ((x0 + 1).min(self.size - 1), (z0 + 1).min(self.size - 1));
+        let (u, v) = (gx - x0 as f32, gz - z0 as f32);
+
+        self.get(x0, z0) * (1. - u) * (1. - v)
+            + self.get(x1, z0) * u * (1. - v)
+            + self.get(x0, z1) * (1. - u) * v
+            + self.get(x1, z1) * u * v
+    }
+
+    pub fn normal_at(&self, x: f32, z: f32) -> Vector3<f32> {
+        let e = self.cell_size;
+        let dx = self.height_at(x + e, z) - self.height_at(x - e, z);
+        let dz = self.height_at(x, z + e) - self.height_at(x, z - e);
+        Vector3::new(-dx, 2. * e, -dz).normalize()
+    }
+
+    fn get(&self, x: usize, z: usize) -> f32 {
+        self.heights[z * self.size + x]
+    }
+}
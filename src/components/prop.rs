@@ -0,0 +1,67 @@
+use amethyst::{
+    assets::{PrefabData, ProgressCounter},
+    core::{math::Vector3, Named},
+    derive::PrefabData,
+    ecs::{Component, DenseVecStorage, Entity},
+    renderer::rendy::mesh::{Normal, Position, TexCoord},
+    renderer::{
+        formats::GraphicsPrefab,
+        rendy::mesh::MeshBuilder,
+        shape::{FromShape, Shape},
+    },
+    Error,
+};
+use serde::{Deserialize, Serialize};
+
+// scenery scattered over terrain chunks, the transform is set by the scatter system
+// props with a `batch` shape are merged into one mesh per chunk instead of being instantiated
+#[derive(Deserialize, Debug, Default, Serialize, PrefabData)]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+pub struct PropPrefabData<V = (Vec<Position>, Vec<Normal>, Vec<TexCoord>)>
+where
+    V: FromShape + Into<MeshBuilder<'static>>,
+{
+    pub name: Option<Named>,
+    graphics: Option<GraphicsPrefab<V>>,
+    pub batch: Option<PropShape>,
+}
+
+// geometry and color of a batched prop
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct PropShape {
+    pub shape: Shape,
+    #[serde(default)]
+    pub scale: Option<(f32, f32, f32)>,
+    pub color: (f32, f32, f32, f32),
+}
+
+// only read by the scatter system when merging, nothing is added to the entity
+impl<'a> PrefabData<'a> for PropShape {
+    type SystemData = ();
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        _: Entity,
+        _: &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+// merged mesh of all props of one kind on a chunk
+#[derive(Debug)]
+pub struct PropBatch {
+    pub chunk_center: Vector3<f32>,
+    pub props: usize,
+}
+
+impl Component for PropBatch {
+    type Storage = DenseVecStorage<Self>;
+}
+
+named_prefab!(PropPrefabData);
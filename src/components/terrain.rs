@@ -1,17 +1,20 @@
-use amethyst::ecs::{Component, DenseVecStorage};
+use crate::utils::coords::ChunkCoord;
+use amethyst::{
+    core::math::Vector3,
+    ecs::{Component, DenseVecStorage, Entity, NullStorage},
+};
 
 #[derive(Debug)]
 pub struct Chunk {
-    pub x: f32,
-    pub y: f32,
+    pub coord: ChunkCoord,
 }
 impl Component for Chunk {
     type Storage = DenseVecStorage<Self>;
 }
 
 impl Chunk {
-    pub fn new(x: f32, y: f32) -> Self {
-        Self { x: x, y: y }
+    pub fn new(coord: ChunkCoord) -> Self {
+        Self { coord }
     }
 }
 
@@ -49,3 +52,61 @@ impl VoxelData {
         Self { voxels: data }
     }
 }
+
+// axis aligned box around a chunk mesh, relative to the chunk transform
+#[derive(Clone, Debug)]
+pub struct ChunkBounds {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Component for ChunkBounds {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl ChunkBounds {
+    // mesh vertices are averages of the voxel heights, so these bound the mesh too
+    pub fn from_voxels(voxels: &VoxelData, chunk_length: f32) -> Self {
+        let (low, high) = voxels.voxels.iter().flat_map(|v| v.heights.iter()).fold(
+            (std::f32::INFINITY, std::f32::NEG_INFINITY),
+            |(low, high), &h| (low.min(h), high.max(h)),
+        );
+        let half = chunk_length / 2.;
+        Self {
+            min: Vector3::new(-half, low, -half),
+            max: Vector3::new(half, high, half),
+        }
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) / 2.
+    }
+
+    pub fn radius(&self) -> f32 {
+        (self.max - self.min).norm() / 2.
+    }
+}
+
+// chunk hidden behind nearer terrain, its mesh is not built until it becomes visible
+#[derive(Debug, Default)]
+pub struct Occluded;
+
+impl Component for Occluded {
+    type Storage = NullStorage<Self>;
+}
+
+// water surface of a chunk, `None` if the chunk is entirely above sea level
+#[derive(Debug)]
+pub struct ChunkWater {
+    pub surface: Option<Entity>,
+}
+
+impl Component for ChunkWater {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl ChunkWater {
+    pub fn new(surface: Option<Entity>) -> Self {
+        Self { surface }
+    }
+}
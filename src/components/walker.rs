@@ -0,0 +1,26 @@
+use amethyst::{
+    core::math::Vector3,
+    ecs::{Component, DenseVecStorage},
+};
+
+// state of an entity controlled in walk mode, removed when switching back to flying
+#[derive(Debug)]
+pub struct WalkState {
+    pub position: Vector3<f32>,
+    pub vertical_speed: f32,
+    pub grounded: bool,
+}
+
+impl Component for WalkState {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl WalkState {
+    pub fn new(position: Vector3<f32>) -> Self {
+        Self {
+            position,
+            vertical_speed: 0.,
+            grounded: false,
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use amethyst::{
-    assets::{PrefabLoaderSystemDesc, Processor},
+    assets::Processor,
     audio::Source,
     controls::FlyControlBundle,
     core::transform::TransformBundle,
@@ -13,43 +13,63 @@ use amethyst::{
     ui::{RenderUi, UiBundle},
     utils::{application_root_dir, auto_fov::AutoFovSystem, fps_counter::FpsCounterBundle},
 };
-use components::{critter::CritterPrefabData, level::LevelPrefabData};
+use components::{critter::CritterPrefabData, level::LevelPrefabData, prop::PropPrefabData};
+use resources::prefabs::PrefabRegistryBundle;
+use utils::manifest::AssetKind;
 
 mod bindings;
+mod cli;
 mod components;
 mod resources;
 mod states;
 mod systems;
 mod utils;
-use noise::utils::*;
-use noise::{Blend, Fbm, Perlin, RidgedMulti};
 
 fn main() -> amethyst::Result<()> {
-    let perlin = Perlin::new();
-
-    PlaneMapBuilder::new(&perlin)
-        .build()
-        .write_to_file("blend.png");
     amethyst::start_logger(Default::default());
 
+    match cli::parse(std::env::args().skip(1)) {
+        Ok(cli::Command::Game) => run_game(),
+        Ok(cli::Command::TerrainPreview(options)) => {
+            if let Err(e) = cli::terrain_preview(&options) {
+                log::error!("Failed to render terrain preview: {}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Ok(cli::Command::GenerateManifest(out)) => {
+            if let Err(e) = utils::manifest::generate_manifest(out) {
+                log::error!("Failed to generate asset manifest: {}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!("{}\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn run_game() -> amethyst::Result<()> {
     let app_root = application_root_dir()?;
     let config_dir = app_root.join("config");
     let assets_dir = app_root.join("assets");
 
     let display_config_path = config_dir.join("display.ron");
     let input_bindings_path = config_dir.join("input.ron");
+    let scatter_config_path = config_dir.join("scatter.ron");
+    let terrain_config_path = config_dir.join("terrain.ron");
+    let prefab_config_path = config_dir.join("prefabs.ron");
 
     let game_data = GameDataBuilder::default()
-        .with_system_desc(
-            PrefabLoaderSystemDesc::<CritterPrefabData>::default(),
-            "",
-            &[],
-        )
-        .with_system_desc(
-            PrefabLoaderSystemDesc::<LevelPrefabData>::default(),
-            "",
-            &[],
-        )
+        .with_bundle(
+            PrefabRegistryBundle::<CritterPrefabData>::new(AssetKind::Critter).with_variants(),
+        )?
+        .with_bundle(
+            PrefabRegistryBundle::<LevelPrefabData>::new(AssetKind::Level).with_on_demand_loading(),
+        )?
+        .with_bundle(PrefabRegistryBundle::<PropPrefabData>::new(AssetKind::Prop))?
         .with_bundle(
             FlyControlBundle::<bindings::GameBindings>::new(
                 Some(bindings::AxisBinding::XAxis),
@@ -59,8 +79,15 @@ fn main() -> amethyst::Result<()> {
             .with_sensitivity(0.1, 0.1)
             .with_speed(250.),
         )?
+        .with(
+            systems::walk_controller::WalkControllerSystem::default(),
+            "walk_movement",
+            &["fly_movement"],
+        )
         .with(AutoFovSystem::new(), "auto_fov", &[])
-        .with_bundle(TransformBundle::new().with_dep(&["fly_movement", "free_rotation"]))?
+        .with_bundle(
+            TransformBundle::new().with_dep(&["fly_movement", "free_rotation", "walk_movement"]),
+        )?
         .with_bundle(UiBundle::<bindings::GameBindings>::new())?
         .with(Processor::<Source>::new(), "source_processor", &[])
         .with(
@@ -69,7 +96,12 @@ fn main() -> amethyst::Result<()> {
             &[],
         )
         .with(systems::debug::DebugSystem::default(), "debug_system", &[])
-        .with_bundle(systems::terrain::TerrainBundle::default())?
+        .with_bundle(
+            systems::terrain::TerrainBundle::default()
+                .with_settings(systems::terrain::TerrainSettings::load(&terrain_config_path))
+                .with_scatter_config(systems::terrain::ScatterConfig::load(&scatter_config_path)),
+        )?
+        .with_bundle(systems::physics::PhysicsBundle)?
         .with_bundle(FpsCounterBundle::default())?
         .with_bundle(
             InputBundle::<bindings::GameBindings>::new()
@@ -86,7 +118,14 @@ fn main() -> amethyst::Result<()> {
                 .with_plugin(RenderUi::default()),
         )?;
 
+    #[cfg(feature = "dev")]
+    let game_data = game_data.with_bundle(systems::hot_reload::HotReloadBundle::new(
+        assets_dir.clone(),
+        config_dir.clone(),
+    ))?;
+
     let mut game = Application::build(assets_dir, states::loading::LoadingState::default())?
+        .with_resource(resources::prefabs::PrefabConfig::load(&prefab_config_path))
         .build(game_data)?;
 
     game.run();
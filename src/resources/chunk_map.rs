@@ -0,0 +1,32 @@
+use crate::utils::coords::ChunkCoord;
+use amethyst::ecs::Entity;
+use std::collections::HashMap;
+
+// loaded chunk entities by coordinate
+// the chunk spawner inserts and the garbage collector removes, everything else should only read
+#[derive(Default)]
+pub struct ChunkMap {
+    chunks: HashMap<ChunkCoord, Entity>,
+}
+
+impl ChunkMap {
+    pub fn get(&self, coord: ChunkCoord) -> Option<Entity> {
+        self.chunks.get(&coord).cloned()
+    }
+
+    pub fn contains(&self, coord: ChunkCoord) -> bool {
+        self.chunks.contains_key(&coord)
+    }
+
+    pub fn insert(&mut self, coord: ChunkCoord, entity: Entity) -> Option<Entity> {
+        self.chunks.insert(coord, entity)
+    }
+
+    pub fn remove(&mut self, coord: ChunkCoord) -> Option<Entity> {
+        self.chunks.remove(&coord)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ChunkCoord, Entity)> + '_ {
+        self.chunks.iter().map(|(&coord, &entity)| (coord, entity))
+    }
+}
@@ -1,2 +1,4 @@
+pub mod chunk_map;
+pub mod prefab_variants;
 pub mod prefabs;
-pub mod terrain;
\ No newline at end of file
+pub mod terrain;
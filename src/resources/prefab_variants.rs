@@ -0,0 +1,233 @@
+use crate::{
+    components::{NamedPrefab, PrefabVariant},
+    utils::{
+        errors::GameError,
+        manifest::{assets_dir, AssetKind, AssetManifest},
+    },
+};
+use amethyst::assets::{Format, Prefab, RonFormat};
+use serde::Deserialize;
+use std::{collections::HashMap, fs, marker::PhantomData, path::PathBuf};
+
+// the prefabs of one kind as written in their files, used to resolve variants before loading.
+// files that can't be read or parsed are left to the prefab loader, which reports them
+pub struct VariantSources<T> {
+    sources: HashMap<String, (PathBuf, Vec<u8>)>, // by manifest id
+    bases: HashMap<String, String>,               // variant id to the id or name it extends
+    names: HashMap<String, String>,               // name to id
+    marker: PhantomData<T>,
+}
+
+impl<T> VariantSources<T>
+where
+    T: PrefabVariant + NamedPrefab + for<'de> Deserialize<'de> + Default + Send + Sync + 'static,
+{
+    pub fn read(manifest: &AssetManifest, kind: AssetKind) -> Result<Self, GameError> {
+        let assets_dir = assets_dir()?;
+        let sources = manifest.entries(kind).filter_map(|entry| {
+            let path = assets_dir.join(&entry.path);
+            match fs::read(&path) {
+                Ok(source) => Some((entry.id.clone(), path, source)),
+                Err(e) => {
+                    log::warn!("Could not read {:?} to resolve variants: {}", path, e);
+                    None
+                }
+            }
+        });
+        Ok(Self::from_sources(sources))
+    }
+
+    // the id, path and contents of each prefab file
+    fn from_sources(sources: impl IntoIterator<Item = (String, PathBuf, Vec<u8>)>) -> Self {
+        let mut variants = Self {
+            sources: HashMap::new(),
+            bases: HashMap::new(),
+            names: HashMap::new(),
+            marker: PhantomData,
+        };
+
+        for (id, path, source) in sources {
+            let prefab: Result<Prefab<T>, _> = RonFormat.import_simple(source.clone());
+            if let Ok(mut prefab) = prefab {
+                let data = prefab.data_or_default(0);
+                if let Some(name) = data.name() {
+                    variants.names.insert(name.to_owned(), id.clone());
+                }
+                if let Some(base) = data.base() {
+                    variants.bases.insert(id.clone(), base.to_owned());
+                }
+            }
+            variants.sources.insert(id, (path, source));
+        }
+        variants
+    }
+
+    // the merged prefab if `id` is a variant, `None` if its file can be loaded as is.
+    // only the root entity of a variant is used, the base's children are kept
+    pub fn resolve(&self, id: &str) -> Result<Option<Prefab<T>>, GameError> {
+        if !self.bases.contains_key(id) {
+            return Ok(None);
+        }
+
+        // walk up to a prefab that doesn't extend anything
+        let mut chain = vec![id.to_owned()];
+        while let Some(base) = self.bases.get(chain.last().unwrap()) {
+            let base_id = self.find(base).ok_or_else(|| GameError::MissingPrefab {
+                role: format!("base of {}", chain.last().unwrap()),
+                id: base.clone(),
+            })?;
+            let cycle = chain.contains(&base_id);
+            chain.push(base_id);
+            if cycle {
+                return Err(GameError::PrefabCycle { ids: chain });
+            }
+        }
+
+        let mut prefab = self.parse(&chain.pop().unwrap())?;
+        while let Some(variant_id) = chain.pop() {
+            let mut variant = self.parse(&variant_id)?;
+            let variant = std::mem::replace(variant.data_or_default(0), T::default());
+            let root = prefab.data_or_default(0);
+            let base = std::mem::replace(root, T::default());
+            *root = variant.extend(base);
+        }
+        Ok(Some(prefab))
+    }
+
+    fn find(&self, id_or_name: &str) -> Option<String> {
+        if self.sources.contains_key(id_or_name) {
+            Some(id_or_name.to_owned())
+        } else {
+            self.names.get(id_or_name).cloned()
+        }
+    }
+
+    fn parse(&self, id: &str) -> Result<Prefab<T>, GameError> {
+        let (path, source) = &self.sources[id];
+        RonFormat
+            .import_simple(source.clone())
+            .map_err(|e| GameError::InvalidConfig {
+                path: path.clone(),
+                reason: e.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default, Deserialize)]
+    #[serde(default)]
+    struct TestData {
+        base: Option<String>,
+        name: Option<String>,
+        size: Option<f32>,
+        colour: Option<String>,
+    }
+
+    impl NamedPrefab for TestData {
+        fn name(&self) -> Option<&str> {
+            self.name.as_ref().map(String::as_str)
+        }
+    }
+
+    impl PrefabVariant for TestData {
+        fn base(&self) -> Option<&str> {
+            self.base.as_ref().map(String::as_str)
+        }
+
+        fn extend(self, base: Self) -> Self {
+            TestData {
+                base: None,
+                name: self.name.or(base.name),
+                size: self.size.or(base.size),
+                colour: self.colour.or(base.colour),
+            }
+        }
+    }
+
+    // `prefabs` are ids and the ron of their root entity's data
+    fn sources(prefabs: &[(&str, &str)]) -> VariantSources<TestData> {
+        VariantSources::from_sources(prefabs.iter().map(|(id, data)| {
+            let source = format!(
+                "#![enable(implicit_some)]\nPrefab(entities: [(data: ({}))])",
+                data
+            );
+            (id.to_string(), PathBuf::from(id), source.into_bytes())
+        }))
+    }
+
+    fn resolve(sources: &VariantSources<TestData>, id: &str) -> TestData {
+        let mut prefab = sources.resolve(id).unwrap().expect("a variant");
+        std::mem::replace(prefab.data_or_default(0), TestData::default())
+    }
+
+    fn cycle(sources: &VariantSources<TestData>, id: &str) -> Vec<String> {
+        match sources.resolve(id) {
+            Err(GameError::PrefabCycle { ids }) => ids,
+            other => panic!("expected a cycle, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn prefab_without_a_base_is_loaded_as_is() {
+        let sources = sources(&[("rock", "size: 1.0")]);
+
+        assert!(sources.resolve("rock").unwrap().is_none());
+    }
+
+    #[test]
+    fn variant_overrides_its_base() {
+        let sources = sources(&[
+            ("rock", r#"size: 1.0, colour: "grey""#),
+            ("rock_big", r#"base: "rock", size: 4.0"#),
+        ]);
+
+        let resolved = resolve(&sources, "rock_big");
+        assert_eq!(resolved.size, Some(4.0));
+        assert_eq!(resolved.colour, Some("grey".to_owned()));
+    }
+
+    #[test]
+    fn variant_of_a_variant() {
+        // the middle prefab is extended by name rather than by id
+        let sources = sources(&[
+            ("rock", r#"name: "rock", size: 1.0, colour: "grey""#),
+            (
+                "rock_mossy",
+                r#"base: "rock", name: "mossy", colour: "green""#,
+            ),
+            ("rock_mossy_big", r#"base: "mossy", size: 4.0"#),
+        ]);
+
+        let resolved = resolve(&sources, "rock_mossy_big");
+        assert_eq!(resolved.name, Some("mossy".to_owned()));
+        assert_eq!(resolved.size, Some(4.0));
+        assert_eq!(resolved.colour, Some("green".to_owned()));
+    }
+
+    #[test]
+    fn variant_extending_itself() {
+        let sources = sources(&[("rock", r#"base: "rock""#)]);
+
+        assert_eq!(cycle(&sources, "rock"), vec!["rock", "rock"]);
+    }
+
+    #[test]
+    fn variants_extending_each_other() {
+        let sources = sources(&[("a", r#"base: "b""#), ("b", r#"base: "a""#)]);
+
+        assert_eq!(cycle(&sources, "a"), vec!["a", "b", "a"]);
+    }
+
+    #[test]
+    fn missing_base() {
+        let sources = sources(&[("rock_big", r#"base: "rock""#)]);
+
+        match sources.resolve("rock_big") {
+            Err(GameError::MissingPrefab { id, .. }) => assert_eq!(id, "rock"),
+            other => panic!("expected a missing base, got {:?}", other.map(|_| ())),
+        }
+    }
+}
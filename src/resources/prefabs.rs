@@ -1,142 +1,799 @@
 use crate::{
-    components::{critter::CritterPrefabData, level::LevelPrefabData, NamedPrefab},
-    utils::assets::enumerate_assets,
+    components::{level::LevelPrefabData, NamedPrefab, PrefabVariant},
+    resources::prefab_variants::VariantSources,
+    utils::{
+        errors::{GameError, PrefabRegistryError},
+        manifest::{AssetKind, AssetManifest},
+    },
 };
 use amethyst::{
-    assets::{AssetStorage, Handle, Prefab, PrefabLoader, ProgressCounter, RonFormat},
-    ecs::{World, WorldExt},
+    assets::{
+        AssetStorage, Handle, Prefab, PrefabData, PrefabLoader, PrefabLoaderSystemDesc,
+        ProgressCounter, RonFormat,
+    },
+    core::{SystemBundle, SystemDesc},
+    ecs::{DispatcherBuilder, World, WorldExt},
     ui::{UiLoader, UiPrefab},
+    Error,
 };
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, marker::PhantomData};
 
+// id of a prefab of type `T`, reads from config files as a plain string
+#[derive(Deserialize, Serialize)]
+#[serde(transparent, bound = "")]
+pub struct PrefabId<T> {
+    id: String,
+    #[serde(skip)]
+    marker: PhantomData<T>,
+}
+
+impl<T> PrefabId<T> {
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_owned(),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl<T> Clone for PrefabId<T> {
+    fn clone(&self) -> Self {
+        Self::new(&self.id)
+    }
+}
+
+impl<T> fmt::Debug for PrefabId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PrefabId({:?})", self.id)
+    }
+}
+
+// prefabs used by the states, loaded from `config/prefabs.ron`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PrefabConfig {
+    pub main_menu: PrefabId<UiPrefab>,
+    pub fps_widget: PrefabId<UiPrefab>,
+    pub level: PrefabId<Prefab<LevelPrefabData>>, // manifest id, levels are loaded on demand
+}
+
+impl Default for PrefabConfig {
+    fn default() -> Self {
+        Self {
+            main_menu: PrefabId::new("main_menu"),
+            fps_widget: PrefabId::new("fps"),
+            level: PrefabId::new("default"),
+        }
+    }
+}
+
+impl PrefabConfig {
+    // every configured prefab that can't be found, call once the prefab names are registered
+    pub fn validate(&self, world: &World) -> Vec<GameError> {
+        let ui = world.read_resource::<PrefabRegistry<UiPrefab>>();
+        // levels are loaded on demand, so they can only be checked against the manifest
+        let level_listed = world
+            .read_resource::<AssetManifest>()
+            .entries(AssetKind::Level)
+            .any(|entry| entry.id == self.level.id());
+        let level = if level_listed {
+            None
+        } else {
+            Some(GameError::MissingPrefab {
+                role: "level".to_owned(),
+                id: self.level.id().to_owned(),
+            })
+        };
+
+        vec![
+            ui.require("main_menu", &self.main_menu).err(),
+            ui.require("fps_widget", &self.fps_widget).err(),
+            level,
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+}
+
+// prefabs are registered under their manifest id as soon as loading starts,
+// the name inside the prefab becomes an alias once it has loaded
 #[derive(Default)]
 pub struct PrefabRegistry<T> {
     prefabs: HashMap<String, Handle<T>>,
+    aliases: HashMap<String, String>, // alias -> id
 }
 
 impl<T> PrefabRegistry<T> {
-    pub fn insert(&mut self, name: String, prefab_handle: Handle<T>) {
-        self.prefabs.insert(name, prefab_handle);
+    pub fn insert(
+        &mut self,
+        id: String,
+        prefab_handle: Handle<T>,
+    ) -> Result<(), PrefabRegistryError> {
+        if self.prefabs.contains_key(&id) || self.aliases.contains_key(&id) {
+            return Err(PrefabRegistryError::DuplicateId(id));
+        }
+        self.prefabs.insert(id, prefab_handle);
+        Ok(())
+    }
+
+    pub fn add_alias(&mut self, alias: String, id: &str) -> Result<(), PrefabRegistryError> {
+        if alias == id {
+            return Ok(());
+        }
+        let existing = if self.prefabs.contains_key(&alias) {
+            Some(alias.clone())
+        } else {
+            self.aliases.get(&alias).cloned()
+        };
+        match existing {
+            Some(ref existing) if existing == id => Ok(()),
+            Some(existing) => Err(PrefabRegistryError::DuplicateName {
+                name: alias,
+                first: existing,
+                second: id.to_owned(),
+            }),
+            None => {
+                self.aliases.insert(alias, id.to_owned());
+                Ok(())
+            }
+        }
     }
 
+    // looks up by id first, then by alias
     pub fn get_prefab(&self, name: &str) -> Option<&Handle<T>> {
-        self.prefabs.get(name)
+        self.prefabs
+            .get(name)
+            .or_else(|| self.aliases.get(name).and_then(|id| self.prefabs.get(id)))
+    }
+
+    pub fn get(&self, id: &PrefabId<T>) -> Option<&Handle<T>> {
+        self.get_prefab(id.id())
+    }
+
+    // like `get`, `role` says what the prefab is for in the error
+    pub fn require(&self, role: &str, id: &PrefabId<T>) -> Result<Handle<T>, GameError> {
+        self.get(id)
+            .cloned()
+            .ok_or_else(|| GameError::MissingPrefab {
+                role: role.to_owned(),
+                id: id.id().to_owned(),
+            })
+    }
+
+    // swaps the prefab behind `id`, returning the previous handle
+    // nothing is added if `id` isn't registered, e.g. a level that isn't loaded
+    #[cfg(feature = "dev")]
+    pub fn replace(&mut self, id: &str, prefab_handle: Handle<T>) -> Option<Handle<T>> {
+        self.prefabs
+            .get_mut(id)
+            .map(|handle| std::mem::replace(handle, prefab_handle))
+    }
+
+    // removes the prefab and its aliases, returning the handle
+    pub fn remove(&mut self, id: &str) -> Option<Handle<T>> {
+        self.aliases.retain(|_, target| target != id);
+        self.prefabs.remove(id)
     }
 
+    // keyed by id, aliases are not included
     pub fn get_prefabs(&self) -> &HashMap<String, Handle<T>> {
         &self.prefabs
     }
+}
+
+// registers a prefab type: its loader system, registry and the manifest entries to load
+// ui prefabs are not `PrefabData` and are always loaded by `initialize_prefabs`
+pub struct PrefabRegistryBundle<T> {
+    kind: AssetKind,
+    on_demand: bool,
+    load: Option<LoadFn>,
+    #[cfg(feature = "dev")]
+    reload: Option<ReloadFn>,
+    marker: PhantomData<T>,
+}
+
+impl<T> PrefabRegistryBundle<T> {
+    pub fn new(kind: AssetKind) -> Self {
+        Self {
+            kind,
+            on_demand: false,
+            load: None,
+            #[cfg(feature = "dev")]
+            reload: None,
+            marker: PhantomData,
+        }
+    }
 
-    pub fn set_prefabs(&mut self, prefabs: HashMap<String, Handle<T>>) {
-        self.prefabs = prefabs;
+    // skip these prefabs when loading starts, they are loaded with `load_prefab` when needed
+    pub fn with_on_demand_loading(mut self) -> Self {
+        self.on_demand = true;
+        self
     }
 }
 
-pub fn initialize_prefabs(world: &mut World) -> ProgressCounter {
+impl<T> PrefabRegistryBundle<T>
+where
+    T: PrefabVariant + NamedPrefab + for<'de> Deserialize<'de> + Default + Send + Sync + 'static,
+{
+    // resolve prefabs that extend another prefab of this kind by its `base` before loading them,
+    // doesn't apply to prefabs loaded on demand
+    pub fn with_variants(mut self) -> Self {
+        self.load = Some(load_variant_prefabs::<T>);
+        #[cfg(feature = "dev")]
+        {
+            self.reload = Some(begin_variant_reload::<T>);
+        }
+        self
+    }
+}
+
+impl<'a, 'b, T> SystemBundle<'a, 'b> for PrefabRegistryBundle<T>
+where
+    T: for<'p> PrefabData<'p>
+        + for<'de> Deserialize<'de>
+        + NamedPrefab
+        + Default
+        + Send
+        + Sync
+        + 'static,
+{
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        builder.add(PrefabLoaderSystemDesc::<T>::default().build(world), "", &[]);
+        world.insert(PrefabRegistry::<Prefab<T>>::default());
+        world
+            .entry::<PrefabKinds>()
+            .or_insert_with(PrefabKinds::default)
+            .kinds
+            .push(PrefabKind {
+                asset: self.kind,
+                on_demand: self.on_demand,
+                load: self.load.unwrap_or(load_prefabs::<T>),
+                register_aliases: register_named_aliases::<T>,
+                #[cfg(feature = "dev")]
+                reload: self.reload.unwrap_or(begin_prefab_reload::<T>),
+                #[cfg(feature = "dev")]
+                finish_reload: finish_reloads::<Prefab<T>>,
+            });
+
+        Ok(())
+    }
+}
+
+type LoadFn =
+    fn(&AssetManifest, AssetKind, &mut World, &mut ProgressCounter) -> Result<(), GameError>;
+#[cfg(feature = "dev")]
+type ReloadFn = fn(&mut World, &str, &str);
+
+#[derive(Clone, Copy)]
+struct PrefabKind {
+    asset: AssetKind,
+    on_demand: bool,
+    load: LoadFn,
+    register_aliases: fn(&mut World) -> Vec<GameError>,
+    #[cfg(feature = "dev")]
+    reload: ReloadFn,
+    #[cfg(feature = "dev")]
+    finish_reload: fn(&mut World),
+}
+
+// prefab types added with `PrefabRegistryBundle`
+#[derive(Default)]
+pub struct PrefabKinds {
+    kinds: Vec<PrefabKind>,
+}
+
+pub fn initialize_prefabs(world: &mut World) -> Result<ProgressCounter, GameError> {
     let mut progress_counter = ProgressCounter::new();
+    let manifest = AssetManifest::load_default()?;
 
     // load ui prefabs
     {
         let mut registry = PrefabRegistry::<UiPrefab>::default();
-        registry.prefabs = enumerate_assets("prefabs/ui")
-            .expect("to be able enumerate UI prefabs")
-            .map(|(_, asset_path)| {
-                (
-                    asset_path.to_owned(),
-                    world.exec(|loader: UiLoader<'_>| {
-                        loader.load(asset_path, &mut progress_counter)
-                    }),
-                )
-            })
-            .collect::<HashMap<String, Handle<UiPrefab>>>();
+        for entry in manifest.entries(AssetKind::Ui) {
+            let handle = world.exec(|loader: UiLoader<'_>| {
+                loader.load(entry.path.as_str(), &mut progress_counter)
+            });
+            registry.insert(entry.id.clone(), handle)?;
+        }
         world.insert(registry);
     }
 
-    // load critter prefabs
-    load_prefabs::<CritterPrefabData>("prefabs/critters", world, &mut progress_counter);
+    // load the registered prefab types
+    let kinds = world.read_resource::<PrefabKinds>().kinds.clone();
+    for kind in kinds.into_iter().filter(|kind| !kind.on_demand) {
+        (kind.load)(&manifest, kind.asset, world, &mut progress_counter)?;
+    }
 
-    // load level prefabs
-    load_prefabs::<LevelPrefabData>("prefabs/levels", world, &mut progress_counter);
+    // kept for hot reloading
+    world.insert(manifest);
 
-    progress_counter
+    Ok(progress_counter)
 }
 
-pub fn update_prefab_names(world: &mut World) {
-    {
-        let updated_prefabs = {
+// makes the names given inside the prefabs usable for lookups, call once loading is complete
+// returns the names that are used by more than one prefab
+pub fn register_prefab_aliases(world: &mut World) -> Vec<GameError> {
+    let mut errors = {
+        let aliases = {
             let registry = world.read_resource::<PrefabRegistry<UiPrefab>>();
-            let prefabs = registry.get_prefabs();
-            let mut prefab_resource = world.write_resource::<AssetStorage<UiPrefab>>();
-            let mut new_prefabs = HashMap::new();
-
-            for (_, handle) in prefabs.iter() {
-                let name = prefab_resource
-                    .get_mut(handle)
-                    .and_then(|prefab| prefab.entity(0))
-                    .and_then(|entity| entity.data())
-                    .and_then(|data| data.0.as_ref())
-                    .map(|transform| &transform.id)
-                    .expect("Failed to retrieve prefab name");
-                new_prefabs.insert(name.to_owned(), handle.clone());
-            }
-            new_prefabs
+            let prefab_resource = world.read_resource::<AssetStorage<UiPrefab>>();
+
+            registry
+                .get_prefabs()
+                .iter()
+                .filter_map(|(id, handle)| {
+                    prefab_resource
+                        .get(handle)
+                        .and_then(|prefab| prefab.entities().next())
+                        .and_then(|entity| entity.data())
+                        .and_then(|data| data.0.as_ref())
+                        .map(|transform| (transform.id.clone(), id.clone()))
+                })
+                .collect::<Vec<_>>()
         };
-        world
-            .write_resource::<PrefabRegistry<UiPrefab>>()
-            .set_prefabs(updated_prefabs);
-    }
+        add_aliases(
+            &mut world.write_resource::<PrefabRegistry<UiPrefab>>(),
+            aliases,
+        )
+    };
 
-    my_update_prefab_names::<CritterPrefabData>(world);
-    my_update_prefab_names::<LevelPrefabData>(world);
+    let kinds = world.read_resource::<PrefabKinds>().kinds.clone();
+    for kind in kinds {
+        errors.extend((kind.register_aliases)(world));
+    }
+    errors
 }
 
-fn load_prefabs<T>(path: &str, world: &mut World, pc: &mut ProgressCounter)
+fn load_prefabs<T>(
+    manifest: &AssetManifest,
+    kind: AssetKind,
+    world: &mut World,
+    pc: &mut ProgressCounter,
+) -> Result<(), GameError>
 where
     T: for<'a> Deserialize<'a> + Send + Sync + Default + 'static,
 {
-    let prefab_iter = {
-        enumerate_assets(path)
-            .expect("assets can be enumerated")
-            .map(|(_, asset_path)| {
-                world.exec(|loader: PrefabLoader<'_, T>| {
-                    (
-                        asset_path.to_owned(),
-                        loader.load(asset_path, RonFormat, &mut *pc),
-                    )
-                })
-            })
-    };
+    let mut registry = PrefabRegistry::<Prefab<T>>::default();
+    for entry in manifest.entries(kind) {
+        let handle = world.exec(|loader: PrefabLoader<'_, T>| {
+            loader.load(entry.path.as_str(), RonFormat, &mut *pc)
+        });
+        registry.insert(entry.id.clone(), handle)?;
+    }
+    world.insert(registry);
+    Ok(())
+}
 
+// like `load_prefabs`, but variants are merged onto their base and loaded from the result
+fn load_variant_prefabs<T>(
+    manifest: &AssetManifest,
+    kind: AssetKind,
+    world: &mut World,
+    pc: &mut ProgressCounter,
+) -> Result<(), GameError>
+where
+    T: PrefabVariant + NamedPrefab + for<'de> Deserialize<'de> + Default + Send + Sync + 'static,
+{
+    let sources = VariantSources::<T>::read(manifest, kind)?;
     let mut registry = PrefabRegistry::<Prefab<T>>::default();
-    for (_count, (name, prefab)) in prefab_iter.enumerate() {
-        registry.insert(name, prefab);
+    for entry in manifest.entries(kind) {
+        let handle = match sources.resolve(&entry.id)? {
+            Some(prefab) => {
+                world.exec(|loader: PrefabLoader<'_, T>| loader.load_from_data(prefab, &mut *pc))
+            }
+            None => world.exec(|loader: PrefabLoader<'_, T>| {
+                loader.load(entry.path.as_str(), RonFormat, &mut *pc)
+            }),
+        };
+        registry.insert(entry.id.clone(), handle)?;
     }
     world.insert(registry);
+    Ok(())
 }
 
-fn my_update_prefab_names<T>(world: &mut World)
+pub fn register_named_aliases<T>(world: &mut World) -> Vec<GameError>
 where
     T: Send + Sync + NamedPrefab + 'static,
 {
-    let updated_prefabs = {
-        let creature_prefabs = world.read_resource::<PrefabRegistry<Prefab<T>>>();
-        let prefabs = creature_prefabs.get_prefabs();
-        let mut prefab_resource = world.write_resource::<AssetStorage<Prefab<T>>>();
-        let mut new_prefabs = HashMap::new();
-
-        for (_, handle) in prefabs.iter() {
-            let name = prefab_resource
-                .get_mut(handle)
-                .and_then(|prefab| prefab.entity(0))
-                .and_then(|entity| entity.data())
-                .and_then(|data| data.name())
-                .expect("Failed to retrieve prefab name");
-            new_prefabs.insert(name.to_owned(), handle.clone());
-        }
-        new_prefabs
+    let aliases = {
+        let registry = world.read_resource::<PrefabRegistry<Prefab<T>>>();
+        let prefab_resource = world.read_resource::<AssetStorage<Prefab<T>>>();
+
+        registry
+            .get_prefabs()
+            .iter()
+            .filter_map(|(id, handle)| {
+                prefab_resource
+                    .get(handle)
+                    .and_then(|prefab| prefab.entities().next())
+                    .and_then(|entity| entity.data())
+                    .and_then(|data| data.name())
+                    .map(|name| (name.to_owned(), id.clone()))
+            })
+            .collect::<Vec<_>>()
     };
+    add_aliases(
+        &mut world.write_resource::<PrefabRegistry<Prefab<T>>>(),
+        aliases,
+    )
+}
+
+// starts loading a prefab of a kind registered for on demand loading,
+// the counter is complete right away if it is already loaded
+pub fn load_prefab<T>(
+    world: &mut World,
+    kind: AssetKind,
+    id: &PrefabId<Prefab<T>>,
+) -> Result<ProgressCounter, GameError>
+where
+    T: for<'a> Deserialize<'a> + Send + Sync + Default + 'static,
+{
+    let mut progress = ProgressCounter::new();
+    if world
+        .read_resource::<PrefabRegistry<Prefab<T>>>()
+        .get(id)
+        .is_some()
+    {
+        return Ok(progress);
+    }
+
+    let entry = world
+        .read_resource::<AssetManifest>()
+        .entries(kind)
+        .find(|entry| entry.id == id.id())
+        .cloned()
+        .ok_or_else(|| GameError::MissingPrefab {
+            role: format!("{:?}", kind).to_lowercase(),
+            id: id.id().to_owned(),
+        })?;
+
+    log::info!("Loading {}", entry.path);
+    let handle = world.exec(|loader: PrefabLoader<'_, T>| {
+        loader.load(entry.path.as_str(), RonFormat, &mut progress)
+    });
     world
         .write_resource::<PrefabRegistry<Prefab<T>>>()
-        .set_prefabs(updated_prefabs);
+        .insert(entry.id, handle)?;
+    Ok(progress)
+}
+
+// drops the registry's handle, the prefab and its assets are freed once no entity uses them
+pub fn unload_prefab<T>(world: &mut World, id: &PrefabId<Prefab<T>>)
+where
+    T: Send + Sync + 'static,
+{
+    if world
+        .write_resource::<PrefabRegistry<Prefab<T>>>()
+        .remove(id.id())
+        .is_some()
+    {
+        log::info!("Unloaded prefab {}", id.id());
+    }
+}
+
+fn add_aliases<T>(
+    registry: &mut PrefabRegistry<T>,
+    mut aliases: Vec<(String, String)>,
+) -> Vec<GameError> {
+    // sorted so the reported duplicate doesn't depend on hash map order
+    aliases.sort();
+    aliases
+        .into_iter()
+        .filter_map(|(alias, id)| registry.add_alias(alias, &id).err())
+        .map(GameError::from)
+        .collect()
+}
+
+// starts reloading the prefab at `path` (relative to the assets folder), returns false if it is
+// not in the manifest. `finish_prefab_reloads` swaps it in once loaded
+#[cfg(feature = "dev")]
+pub fn reload_prefab(world: &mut World, path: &str) -> bool {
+    let entry = world
+        .read_resource::<AssetManifest>()
+        .assets
+        .iter()
+        .find(|entry| entry.path == path)
+        .cloned();
+    let entry = match entry {
+        Some(entry) => entry,
+        None => return false,
+    };
+
+    if entry.kind == AssetKind::Ui {
+        if !is_loaded::<UiPrefab>(world, &entry.id) {
+            return true;
+        }
+        let mut progress = ProgressCounter::new();
+        let handle = world.exec(|loader: UiLoader<'_>| loader.load(path, &mut progress));
+        queue_reload::<UiPrefab>(world, entry.id, handle, progress);
+        return true;
+    }
+
+    let kind = world
+        .read_resource::<PrefabKinds>()
+        .kinds
+        .iter()
+        .find(|kind| kind.asset == entry.kind)
+        .cloned();
+    match kind {
+        Some(kind) => {
+            (kind.reload)(world, &entry.id, path);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(feature = "dev")]
+pub fn finish_prefab_reloads(world: &mut World) {
+    finish_reloads::<UiPrefab>(world);
+    let kinds = world.read_resource::<PrefabKinds>().kinds.clone();
+    for kind in kinds {
+        (kind.finish_reload)(world);
+    }
+}
+
+#[cfg(feature = "dev")]
+struct PendingReload<A: amethyst::assets::Asset> {
+    id: String,
+    handle: Handle<A>,
+    progress: ProgressCounter,
+}
+
+#[cfg(feature = "dev")]
+struct PrefabReloads<A: amethyst::assets::Asset> {
+    pending: Vec<PendingReload<A>>,
+}
+
+#[cfg(feature = "dev")]
+impl<A: amethyst::assets::Asset> Default for PrefabReloads<A> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+        }
+    }
+}
+
+#[cfg(feature = "dev")]
+fn begin_prefab_reload<T>(world: &mut World, id: &str, path: &str)
+where
+    T: for<'a> Deserialize<'a> + Send + Sync + Default + 'static,
+{
+    if !is_loaded::<Prefab<T>>(world, id) {
+        return;
+    }
+    let mut progress = ProgressCounter::new();
+    let handle =
+        world.exec(|loader: PrefabLoader<'_, T>| loader.load(path, RonFormat, &mut progress));
+    queue_reload::<Prefab<T>>(world, id.to_owned(), handle, progress);
+}
+
+// variants of a changed base keep their old data until they are changed themselves
+#[cfg(feature = "dev")]
+fn begin_variant_reload<T>(world: &mut World, id: &str, path: &str)
+where
+    T: PrefabVariant + NamedPrefab + for<'de> Deserialize<'de> + Default + Send + Sync + 'static,
+{
+    if !is_loaded::<Prefab<T>>(world, id) {
+        return;
+    }
+    let manifest = (*world.read_resource::<AssetManifest>()).clone();
+    let kind = match manifest.assets.iter().find(|entry| entry.id == id) {
+        Some(entry) => entry.kind,
+        None => return,
+    };
+
+    match VariantSources::<T>::read(&manifest, kind).and_then(|sources| sources.resolve(id)) {
+        Ok(Some(prefab)) => {
+            let mut progress = ProgressCounter::new();
+            let handle = world
+                .exec(|loader: PrefabLoader<'_, T>| loader.load_from_data(prefab, &mut progress));
+            queue_reload::<Prefab<T>>(world, id.to_owned(), handle, progress);
+        }
+        Ok(None) => begin_prefab_reload::<T>(world, id, path),
+        Err(e) => log::error!("Failed to reload prefab {}: {}", id, e),
+    }
+}
+
+// prefabs loaded on demand aren't reloaded while they're unloaded, they are read from disk
+// the next time they're needed anyway
+#[cfg(feature = "dev")]
+fn is_loaded<A: amethyst::assets::Asset>(world: &World, id: &str) -> bool {
+    world
+        .try_fetch::<PrefabRegistry<A>>()
+        .map_or(false, |registry| registry.get_prefabs().contains_key(id))
+}
+
+#[cfg(feature = "dev")]
+fn queue_reload<A: amethyst::assets::Asset>(
+    world: &mut World,
+    id: String,
+    handle: Handle<A>,
+    progress: ProgressCounter,
+) {
+    log::info!("Reloading prefab {}", id);
+    world
+        .entry::<PrefabReloads<A>>()
+        .or_insert_with(PrefabReloads::default)
+        .pending
+        .push(PendingReload {
+            id,
+            handle,
+            progress,
+        });
+}
+
+// replaces reloaded prefabs in the registry and re-instantiates every entity using the old one,
+// the instance keeps its entity but its descendants are recreated
+#[cfg(feature = "dev")]
+fn finish_reloads<A: amethyst::assets::Asset>(world: &mut World) {
+    use amethyst::{
+        core::ParentHierarchy,
+        ecs::{Entities, Join, WriteStorage},
+    };
+
+    let done = {
+        let mut reloads = world
+            .entry::<PrefabReloads<A>>()
+            .or_insert_with(PrefabReloads::default);
+        let (done, pending) = reloads.pending.drain(..).partition::<Vec<_>, _>(|reload| {
+            reload.progress.is_complete() || reload.progress.num_failed() > 0
+        });
+        reloads.pending = pending;
+        done
+    };
+
+    for reload in done {
+        if reload.progress.num_failed() > 0 {
+            log::error!("Failed to reload prefab {}, keeping the old one", reload.id);
+            continue;
+        }
+        let old = match world
+            .write_resource::<PrefabRegistry<A>>()
+            .replace(&reload.id, reload.handle.clone())
+        {
+            Some(old) => old,
+            // unloaded while reloading
+            None => continue,
+        };
+
+        world.exec(
+            |(entities, hierarchy, mut handles): (
+                Entities<'_>,
+                amethyst::ecs::ReadExpect<'_, ParentHierarchy>,
+                WriteStorage<'_, Handle<A>>,
+            )| {
+                let instances = (&*entities, &handles)
+                    .join()
+                    .filter(|(_, handle)| **handle == old)
+                    .map(|(entity, _)| entity)
+                    .collect::<Vec<_>>();
+
+                for entity in instances {
+                    for child in hierarchy.all_children_iter(entity) {
+                        if let Err(e) = entities.delete(child) {
+                            log::warn!("Failed to delete prefab child: {:?}", e);
+                        }
+                    }
+                    // inserting the handle again makes the prefab loader instantiate it
+                    handles.remove(entity);
+                    if let Err(e) = handles.insert(entity, reload.handle.clone()) {
+                        log::warn!("Failed to re-instantiate prefab {}: {:?}", reload.id, e);
+                    }
+                }
+                log::info!("Reloaded prefab {}", reload.id);
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestPrefab = Prefab<()>;
+
+    // a registry with a prefab for each id, and their handles in the same order
+    fn registry(ids: &[&str]) -> (PrefabRegistry<TestPrefab>, Vec<Handle<TestPrefab>>) {
+        let mut storage = AssetStorage::<TestPrefab>::new();
+        let mut registry = PrefabRegistry::default();
+        let handles = ids
+            .iter()
+            .map(|id| {
+                let handle = storage.insert(Prefab::new());
+                registry.insert(id.to_string(), handle.clone()).unwrap();
+                handle
+            })
+            .collect();
+        (registry, handles)
+    }
+
+    fn duplicate_name(name: &str, first: &str, second: &str) -> PrefabRegistryError {
+        PrefabRegistryError::DuplicateName {
+            name: name.to_owned(),
+            first: first.to_owned(),
+            second: second.to_owned(),
+        }
+    }
+
+    #[test]
+    fn duplicate_id_keeps_the_first_prefab() {
+        let (mut registry, handles) = registry(&["tree", "rock"]);
+
+        assert_eq!(
+            registry.insert("tree".to_owned(), handles[1].clone()),
+            Err(PrefabRegistryError::DuplicateId("tree".to_owned()))
+        );
+        assert_eq!(registry.get_prefab("tree"), Some(&handles[0]));
+    }
+
+    #[test]
+    fn duplicate_alias_keeps_the_first_prefab() {
+        let (mut registry, handles) = registry(&["tree_a", "tree_b"]);
+        registry.add_alias("tree".to_owned(), "tree_a").unwrap();
+
+        // registering the same alias again is fine
+        assert_eq!(registry.add_alias("tree".to_owned(), "tree_a"), Ok(()));
+        assert_eq!(
+            registry.add_alias("tree".to_owned(), "tree_b"),
+            Err(duplicate_name("tree", "tree_a", "tree_b"))
+        );
+        assert_eq!(registry.get_prefab("tree"), Some(&handles[0]));
+    }
+
+    #[test]
+    fn alias_clashing_with_an_id() {
+        let (mut registry, handles) = registry(&["rock", "stone"]);
+
+        // a prefab named like another prefab's id
+        assert_eq!(
+            registry.add_alias("rock".to_owned(), "stone"),
+            Err(duplicate_name("rock", "rock", "stone"))
+        );
+        assert_eq!(registry.get_prefab("rock"), Some(&handles[0]));
+
+        // an id already used as a name
+        registry.add_alias("pebble".to_owned(), "stone").unwrap();
+        assert_eq!(
+            registry.insert("pebble".to_owned(), handles[0].clone()),
+            Err(PrefabRegistryError::DuplicateId("pebble".to_owned()))
+        );
+        assert_eq!(registry.get_prefab("pebble"), Some(&handles[1]));
+    }
+
+    #[test]
+    fn remove_drops_the_aliases() {
+        let (mut registry, handles) = registry(&["tree_a"]);
+        registry.add_alias("tree".to_owned(), "tree_a").unwrap();
+
+        assert_eq!(registry.remove("tree_a"), Some(handles[0].clone()));
+        assert_eq!(registry.get_prefab("tree"), None);
+        assert_eq!(registry.add_alias("tree".to_owned(), "tree_b"), Ok(()));
+    }
+
+    #[cfg(feature = "dev")]
+    #[test]
+    fn replace_only_swaps_registered_ids() {
+        let (mut registry, handles) = registry(&["tree", "rock"]);
+
+        assert_eq!(
+            registry.replace("tree", handles[1].clone()),
+            Some(handles[0].clone())
+        );
+        assert_eq!(registry.get_prefab("tree"), Some(&handles[1]));
+
+        // an unloaded level stays unloaded
+        assert_eq!(registry.replace("level", handles[0].clone()), None);
+        assert_eq!(registry.get_prefab("level"), None);
+    }
 }
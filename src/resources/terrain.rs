@@ -0,0 +1,423 @@
+use crate::{
+    components::terrain::VoxelData,
+    resources::chunk_map::ChunkMap,
+    systems::terrain::TerrainSettings,
+    utils::{
+        coords::{ChunkCoord, VoxelCoord},
+        erosion::{apply_passes, pipeline_margin, HeightGrid},
+        errors::HeightmapError,
+        heightmap::Heightmap,
+    },
+};
+use amethyst::{core::math::Vector3, ecs::prelude::*, shred::ResourceId};
+use noise::{NoiseFn, Perlin, Seedable};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const DEFAULT_SEED: u32 = 20;
+
+enum HeightSource {
+    Noise(Perlin),
+    Heightmap(Heightmap),
+}
+
+// the terrain height function, shared by voxel generation and gameplay queries
+pub struct HeightGenerator {
+    seed: u32,
+    source: HeightSource,
+}
+
+impl Default for HeightGenerator {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEED)
+    }
+}
+
+impl HeightGenerator {
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            source: HeightSource::Noise(Perlin::new().set_seed(seed)),
+        }
+    }
+
+    // the seed is still used by post-processing passes
+    pub fn from_heightmap(seed: u32, heightmap: Heightmap) -> Self {
+        Self {
+            seed,
+            source: HeightSource::Heightmap(heightmap),
+        }
+    }
+
+    // noise, or the heightmap set in `settings` (its path is relative to `root`)
+    pub fn from_settings(
+        seed: u32,
+        settings: &TerrainSettings,
+        root: &Path,
+    ) -> Result<Self, HeightmapError> {
+        match &settings.heightmap {
+            Some(heightmap) => Ok(Self::from_heightmap(seed, heightmap.load(root)?)),
+            None => Ok(Self::new(seed)),
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn get_height(&self, x: f32, y: f32) -> f32 {
+        match &self.source {
+            HeightSource::Noise(noise) => {
+                (noise.get([x as f64 / 100., y as f64 / 100.]) * 30.) as f32
+            }
+            HeightSource::Heightmap(heightmap) => heightmap.get_height(x, y),
+        }
+    }
+
+    // post-processed heights of a chunk, with `margin` extra voxels on each side;
+    // returns the grid and the margin
+    pub fn chunk_heights(
+        &self,
+        settings: &TerrainSettings,
+        chunk: ChunkCoord,
+    ) -> (HeightGrid, i32) {
+        // one voxel of overlap for the neighbour heights plus whatever the post-processing needs,
+        // so that chunks agree on heights along their borders
+        let margin = pipeline_margin(&settings.post_process) as i32 + 1;
+        let origin = chunk
+            .origin_voxel(settings)
+            .offset(-margin, -margin)
+            .corner(settings);
+
+        let mut grid = HeightGrid::generate(
+            (origin.x, origin.z),
+            (settings.chunk_size + margin * 2) as usize,
+            settings.voxel_size,
+            |x, y| self.get_height(x, y),
+        );
+        apply_passes(&mut grid, &settings.post_process, self.seed);
+
+        (grid, margin)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Biome {
+    Water,
+    Beach,
+    Grass,
+    Rock,
+    Snow,
+}
+
+impl Biome {
+    // `steepness` is 0 for flat ground and 1 for a vertical wall (1 - normal.y)
+    pub fn classify(height: f32, steepness: f32, settings: &TerrainSettings) -> Biome {
+        if height < settings.sea_level {
+            Biome::Water
+        } else if height < settings.sea_level + 2. {
+            Biome::Beach
+        } else if steepness > 0.3 {
+            Biome::Rock
+        } else if height > 20. {
+            Biome::Snow
+        } else {
+            Biome::Grass
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RayHit {
+    pub point: Vector3<f32>,
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+// ground height lookups against the loaded chunks, falling back to the (unprocessed) height
+// function where no voxels have been generated yet
+// heights are stored at voxel centers, which is where the chunk meshes place them
+#[derive(SystemData)]
+pub struct TerrainQuery<'a> {
+    settings: Read<'a, TerrainSettings>,
+    generator: ReadExpect<'a, HeightGenerator>,
+    chunk_map: Read<'a, ChunkMap>,
+    voxel_data: ReadStorage<'a, VoxelData>,
+}
+
+impl<'a> TerrainQuery<'a> {
+    pub fn seed(&self) -> u32 {
+        self.generator.seed()
+    }
+
+    // ground height at (x, z), bilinearly interpolated between voxel centers
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let half_chunk = self.settings.chunk_size as f32 / 2.;
+        let gx = x / self.settings.voxel_size + half_chunk - 0.5;
+        let gz = z / self.settings.voxel_size + half_chunk - 0.5;
+        let (x0, z0) = (gx.floor(), gz.floor());
+        let (u, v) = (gx - x0, gz - z0);
+        let voxel = VoxelCoord(x0 as i32, z0 as i32);
+
+        self.voxel_height(voxel) * (1. - u) * (1. - v)
+            + self.voxel_height(voxel.offset(1, 0)) * u * (1. - v)
+            + self.voxel_height(voxel.offset(0, 1)) * (1. - u) * v
+            + self.voxel_height(voxel.offset(1, 1)) * u * v
+    }
+
+    pub fn normal_at(&self, x: f32, z: f32) -> Vector3<f32> {
+        let e = self.settings.voxel_size;
+        let dx = self.height_at(x + e, z) - self.height_at(x - e, z);
+        let dz = self.height_at(x, z + e) - self.height_at(x, z - e);
+        Vector3::new(-dx, 2. * e, -dz).normalize()
+    }
+
+    // first intersection of the ray with the ground within `max_dist`
+    pub fn raycast(
+        &self,
+        origin: &Vector3<f32>,
+        dir: &Vector3<f32>,
+        max_dist: f32,
+    ) -> Option<RayHit> {
+        let dir = dir.normalize();
+        let above = |t: f32| {
+            let p = origin + dir * t;
+            p.y - self.height_at(p.x, p.z)
+        };
+
+        if above(0.) <= 0. {
+            return Some(self.hit(origin, &dir, 0.));
+        }
+
+        // march in half voxel steps, then refine the crossing with a bisection
+        let step = self.settings.voxel_size / 2.;
+        let mut previous = 0.;
+        let mut t = step.min(max_dist);
+        loop {
+            if above(t) <= 0. {
+                let (mut low, mut high) = (previous, t);
+                for _ in 0..16 {
+                    let mid = (low + high) / 2.;
+                    if above(mid) > 0. {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                return Some(self.hit(origin, &dir, high));
+            }
+            if t >= max_dist {
+                return None;
+            }
+            previous = t;
+            t = (t + step).min(max_dist);
+        }
+    }
+
+    fn hit(&self, origin: &Vector3<f32>, dir: &Vector3<f32>, distance: f32) -> RayHit {
+        let point = origin + dir * distance;
+        RayHit {
+            normal: self.normal_at(point.x, point.z),
+            point,
+            distance,
+        }
+    }
+
+    fn voxel_height(&self, voxel: VoxelCoord) -> f32 {
+        let chunk_coord = voxel.chunk(&self.settings);
+        let (x, z) = voxel.local(&self.settings);
+
+        let loaded = self
+            .chunk_map
+            .get(chunk_coord)
+            .and_then(|entity| self.voxel_data.get(entity))
+            .and_then(|voxels| {
+                voxels
+                    .voxels
+                    .get((z * self.settings.chunk_size + x) as usize)
+            });
+
+        match loaded {
+            Some(v) => v.heights[0],
+            None => {
+                let corner = voxel.corner(&self.settings);
+                self.generator.get_height(corner.x, corner.z)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::terrain::Voxel;
+
+    const CHUNK_SIZE: i32 = 4;
+    const VOXEL_SIZE: f32 = 2.;
+
+    fn settings() -> TerrainSettings {
+        TerrainSettings {
+            chunk_size: CHUNK_SIZE,
+            voxel_size: VOXEL_SIZE,
+            ..TerrainSettings::default()
+        }
+    }
+
+    // the height function is `slope * x + height` around the origin (within 30 units)
+    fn generator(slope: f32, height: f32) -> HeightGenerator {
+        let values = (0..64).map(|x| x as f32 / 63.).collect();
+        let heightmap = Heightmap::new(64, 1, values)
+            .unwrap()
+            .with_height(slope * 63., height - slope * 32.);
+        HeightGenerator::from_heightmap(DEFAULT_SEED, heightmap)
+    }
+
+    fn world(generator: HeightGenerator) -> World {
+        let mut world = World::new();
+        world.register::<VoxelData>();
+        world.insert(settings());
+        world.insert(generator);
+        world.insert(ChunkMap::default());
+        world
+    }
+
+    // loads chunk (0, 0) with the voxel at local (x, z) having height `height(x, z)`
+    fn load_chunk<F: Fn(i32, i32) -> f32>(world: &mut World, height: F) {
+        let voxels = (0..CHUNK_SIZE * CHUNK_SIZE)
+            .map(|i| {
+                let (x, z) = (i % CHUNK_SIZE, i / CHUNK_SIZE);
+                Voxel::new(x, z, 0., 0., vec![height(x, z); 9])
+            })
+            .collect();
+        let entity = world.create_entity().with(VoxelData::new(voxels)).build();
+        world
+            .write_resource::<ChunkMap>()
+            .insert(ChunkCoord(0, 0), entity);
+    }
+
+    // world position of the center of voxel (x, z) in chunk (0, 0)
+    fn voxel_center(x: i32, z: i32) -> (f32, f32) {
+        let center = VoxelCoord(x, z).center(&settings());
+        (center.x, center.z)
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    fn assert_close_vec(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!(
+            (actual - expected).norm() < 1e-3,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn height_at_interpolates_between_voxel_centers() {
+        let mut world = world(generator(0., 0.));
+        load_chunk(&mut world, |x, z| (x * 10 + z) as f32);
+        let query = world.system_data::<TerrainQuery>();
+
+        let (x, z) = voxel_center(1, 1);
+        assert_close(query.height_at(x, z), 11.);
+        // halfway to the next voxel along x
+        assert_close(query.height_at(x + VOXEL_SIZE / 2., z), 16.);
+        // a quarter of the way along z
+        assert_close(query.height_at(x, z + VOXEL_SIZE / 4.), 11.25);
+        // between four voxel centers
+        assert_close(
+            query.height_at(x + VOXEL_SIZE / 2., z + VOXEL_SIZE / 2.),
+            (11. + 21. + 12. + 22.) / 4.,
+        );
+    }
+
+    #[test]
+    fn unloaded_chunks_use_the_height_function() {
+        let mut world = world(generator(0., 3.));
+        load_chunk(&mut world, |_, _| 100.);
+        let query = world.system_data::<TerrainQuery>();
+
+        let (x, z) = voxel_center(1, 2);
+        assert_close(query.height_at(x, z), 100.);
+        // two chunks over, nothing is loaded there
+        let far = x + 2. * settings().chunk_length();
+        assert_close(query.height_at(far, z), 3.);
+    }
+
+    #[test]
+    fn normal_of_plane_points_up() {
+        let world = world(generator(0., 3.));
+        let query = world.system_data::<TerrainQuery>();
+
+        assert_close_vec(query.normal_at(0., 0.), Vector3::y());
+        assert_close_vec(query.normal_at(-5., 7.), Vector3::y());
+    }
+
+    #[test]
+    fn normal_of_slope_leans_downhill() {
+        let world = world(generator(0.5, 0.));
+        let query = world.system_data::<TerrainQuery>();
+
+        let expected = Vector3::new(-0.5, 1., 0.).normalize();
+        assert_close_vec(query.normal_at(0., 0.), expected);
+        assert_close_vec(query.normal_at(3., -4.), expected);
+    }
+
+    #[test]
+    fn raycast_hits_ground() {
+        let world = world(generator(0., 3.));
+        let query = world.system_data::<TerrainQuery>();
+
+        let down = query
+            .raycast(&Vector3::new(1., 10., 2.), &-Vector3::y(), 100.)
+            .expect("ray straight down should hit");
+        assert_close_vec(down.point, Vector3::new(1., 3., 2.));
+        assert_close(down.distance, 7.);
+        assert_close_vec(down.normal, Vector3::y());
+
+        let diagonal = query
+            .raycast(&Vector3::new(0., 10., 0.), &Vector3::new(1., -1., 0.), 100.)
+            .expect("diagonal ray should hit");
+        assert_close_vec(diagonal.point, Vector3::new(7., 3., 0.));
+        assert_close(diagonal.distance, 7. * 2f32.sqrt());
+    }
+
+    #[test]
+    fn raycast_hits_loaded_chunk() {
+        let mut world = world(generator(0., 3.));
+        load_chunk(&mut world, |_, _| -2.);
+        let query = world.system_data::<TerrainQuery>();
+
+        let (x, z) = voxel_center(2, 2);
+        let hit = query
+            .raycast(&Vector3::new(x, 10., z), &-Vector3::y(), 100.)
+            .expect("ray should hit the loaded chunk");
+        assert_close(hit.point.y, -2.);
+    }
+
+    #[test]
+    fn raycast_misses() {
+        let world = world(generator(0., 3.));
+        let query = world.system_data::<TerrainQuery>();
+
+        // parallel to the ground
+        assert!(query
+            .raycast(&Vector3::new(0., 10., 0.), &Vector3::x(), 50.)
+            .is_none());
+        // pointing away from it
+        assert!(query
+            .raycast(&Vector3::new(0., 10., 0.), &Vector3::y(), 50.)
+            .is_none());
+        // the ground is further than the max distance
+        assert!(query
+            .raycast(&Vector3::new(0., 10., 0.), &-Vector3::y(), 5.)
+            .is_none());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::utils::hierarchy_util;
+use amethyst::{
+  ecs::{Entity, WriteStorage},
+  input::is_key_down,
+  prelude::*,
+  ui::{UiCreator, UiFinder, UiText},
+  winit::VirtualKeyCode,
+};
+
+// shown when the game can't start, lists what went wrong until the player quits
+pub struct ErrorState {
+  messages: Vec<String>,
+  ui_root: Option<Entity>,
+  shown: bool,
+}
+
+impl ErrorState {
+  pub fn new(messages: Vec<String>) -> Self {
+    Self {
+      messages,
+      ui_root: None,
+      shown: false,
+    }
+  }
+}
+
+impl SimpleState for ErrorState {
+  fn on_start(&mut self, data: StateData<GameData>) {
+    self.ui_root = Some(
+      data
+        .world
+        .exec(|mut creator: UiCreator<'_>| creator.create("loader/error.ron", ())),
+    );
+  }
+
+  fn on_stop(&mut self, data: StateData<GameData>) {
+    if let Some(root) = self.ui_root {
+      if let Err(e) = hierarchy_util::delete_hierarchy(root, data.world) {
+        log::warn!("Failed to delete error ui: {:?}", e);
+      }
+    }
+    self.ui_root = None;
+  }
+
+  fn handle_event(&mut self, _: StateData<GameData>, event: StateEvent) -> SimpleTrans {
+    if let StateEvent::Window(event) = &event {
+      if is_key_down(&event, VirtualKeyCode::Escape) {
+        return Trans::Quit;
+      }
+    }
+    Trans::None
+  }
+
+  fn update(&mut self, data: &mut StateData<GameData>) -> SimpleTrans {
+    // the label only exists once the ui has loaded
+    if !self.shown {
+      let messages = self.messages.join("\n");
+      self.shown = data.world.exec(
+        |(finder, mut texts): (UiFinder<'_>, WriteStorage<'_, UiText>)| {
+          match finder.find("error_text").and_then(|e| texts.get_mut(e)) {
+            Some(text) => {
+              text.text = messages;
+              true
+            }
+            None => false,
+          }
+        },
+      );
+    }
+    Trans::None
+  }
+}
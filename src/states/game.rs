@@ -1,5 +1,8 @@
 use crate::{
-    components::level::LevelPrefabData, resources::prefabs::PrefabRegistry, utils::hierarchy_util,
+    components::level::LevelPrefabData,
+    resources::prefabs::{unload_prefab, PrefabConfig, PrefabId, PrefabRegistry},
+    states::{error::ErrorState, menu::MenuState},
+    utils::{errors::GameError, hierarchy_util},
 };
 use amethyst::{
     assets::{AssetLoaderSystemData, Handle, Prefab},
@@ -22,42 +25,58 @@ use amethyst::{
 };
 
 pub struct MainGameState {
+    level: PrefabId<Prefab<LevelPrefabData>>,
     scene: Option<Entity>,
     fps_display: Option<Entity>,
+    error: Option<GameError>, // reported by switching to the error state
 }
 
-impl Default for MainGameState {
-    fn default() -> Self {
+impl MainGameState {
+    // the level has to be loaded already, see `LevelLoadingState`
+    pub fn new(level: PrefabId<Prefab<LevelPrefabData>>) -> Self {
         Self {
+            level,
             scene: None,
             fps_display: None,
+            error: None,
         }
     }
 }
 pub struct Hax {
     pub the_material: Option<Handle<Material>>,
+    pub water_material: Option<Handle<Material>>,
 }
 
 impl Default for Hax {
     fn default() -> Self {
-        Self { the_material: None }
+        Self {
+            the_material: None,
+            water_material: None,
+        }
     }
 }
 
 impl SimpleState for MainGameState {
     fn on_start(&mut self, data: StateData<GameData>) {
         let StateData { world, .. } = data;
-        let scene_handle = world
+        // the configured prefabs are validated by the loading state
+        let config = (*world.read_resource::<PrefabConfig>()).clone();
+        let prefabs = world
             .read_resource::<PrefabRegistry<Prefab<LevelPrefabData>>>()
-            .get_prefab("default_level")
-            .expect("level prefab not found")
-            .clone();
-
-        let menu_prefab = world
-            .read_resource::<PrefabRegistry<UiPrefab>>()
-            .get_prefab("fps_widget") // todo: move ids to config file
-            .expect("fps prefab not found")
-            .clone();
+            .require("level", &self.level)
+            .and_then(|scene| {
+                world
+                    .read_resource::<PrefabRegistry<UiPrefab>>()
+                    .require("fps_widget", &config.fps_widget)
+                    .map(|fps| (scene, fps))
+            });
+        let (scene_handle, menu_prefab) = match prefabs {
+            Ok(prefabs) => prefabs,
+            Err(e) => {
+                self.error = Some(e);
+                return;
+            }
+        };
 
         self.scene = Some(world.create_entity().with(scene_handle).build());
         self.fps_display = Some(world.create_entity().with(menu_prefab.clone()).build());
@@ -81,9 +100,27 @@ impl SimpleState for MainGameState {
                     (),
                 )
             });
+
+            let water_albedo = world.exec(|loader: AssetLoaderSystemData<Texture>| {
+                loader.load_from_data(
+                    load_from_linear_rgba(LinSrgba::new(0.1, 0.3, 0.8, 0.6)).into(),
+                    (),
+                )
+            });
+
+            let water_mat = world.exec(|loader: AssetLoaderSystemData<Material>| {
+                loader.load_from_data(
+                    Material {
+                        albedo: water_albedo,
+                        ..default_mat.clone()
+                    },
+                    (),
+                )
+            });
             {
                 let mut hax = world.write_resource::<Hax>();
                 hax.the_material = Some(mat.clone());
+                hax.water_material = Some(water_mat.clone());
             }
         }
     }
@@ -91,10 +128,32 @@ impl SimpleState for MainGameState {
     fn on_stop(&mut self, data: StateData<GameData>) {
         // delete the ui and scene
         if let Some(root) = self.scene {
-            hierarchy_util::delete_hierarchy(root, data.world).expect("failed to delete scene");
+            if let Err(e) = hierarchy_util::delete_hierarchy(root, data.world) {
+                log::warn!("Failed to delete scene: {:?}", e);
+            }
+        }
+        if let Some(root) = self.fps_display {
+            if let Err(e) = hierarchy_util::delete_hierarchy(root, data.world) {
+                log::warn!("Failed to delete fps display: {:?}", e);
+            }
         }
 
         self.scene = None;
+        self.fps_display = None;
+        data.world.write_resource::<HideCursor>().hide = false;
+
+        // nothing references the level anymore, so its assets can be dropped
+        unload_prefab(data.world, &self.level);
+    }
+
+    fn update(&mut self, _: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+        match self.error.take() {
+            Some(e) => {
+                log::error!("{}", e);
+                Trans::Switch(Box::new(ErrorState::new(vec![e.to_string()])))
+            }
+            None => Trans::None,
+        }
     }
 
     fn handle_event(
@@ -105,7 +164,11 @@ impl SimpleState for MainGameState {
         let StateData { world, .. } = data;
         if let StateEvent::Window(event) = &event {
             if is_key_down(&event, VirtualKeyCode::Escape) {
+                // the first press frees the cursor, the second leaves the level
                 let mut hide_cursor = world.write_resource::<HideCursor>();
+                if !hide_cursor.hide {
+                    return Trans::Switch(Box::new(MenuState::default()));
+                }
                 hide_cursor.hide = false;
             } else if is_mouse_button_down(&event, MouseButton::Left) {
                 let mut hide_cursor = world.write_resource::<HideCursor>();
@@ -0,0 +1,74 @@
+use crate::{
+  components::level::LevelPrefabData,
+  resources::prefabs::{load_prefab, register_named_aliases, PrefabId},
+  states::{
+    error::ErrorState,
+    game::MainGameState,
+    loading::{LoadingProgress, LoadingStatus},
+  },
+  utils::{hierarchy_util, manifest::AssetKind},
+};
+use amethyst::{assets::Prefab, ecs::Entity, prelude::*, ui::UiCreator};
+use log::{error, info, warn};
+
+// loads a single level on demand and starts it, reusing the loader ui
+pub struct LevelLoadingState {
+  level: PrefabId<Prefab<LevelPrefabData>>,
+  ui_root: Option<Entity>,
+  progress: LoadingProgress,
+}
+
+impl LevelLoadingState {
+  pub fn new(level: PrefabId<Prefab<LevelPrefabData>>) -> Self {
+    Self {
+      level,
+      ui_root: None,
+      progress: LoadingProgress::default(),
+    }
+  }
+}
+
+impl SimpleState for LevelLoadingState {
+  fn on_start(&mut self, data: StateData<GameData>) {
+    let StateData { mut world, .. } = data;
+
+    self.ui_root =
+      Some(world.exec(|mut creator: UiCreator<'_>| creator.create("loader/ui.ron", ())));
+
+    match load_prefab(&mut world, AssetKind::Level, &self.level) {
+      Ok(counter) => self.progress.start(counter),
+      Err(e) => {
+        error!("Failed to start loading level {}: {}", self.level.id(), e);
+        self.progress.fail(e.to_string());
+      }
+    }
+  }
+
+  fn on_stop(&mut self, data: StateData<GameData>) {
+    if let Some(root) = self.ui_root {
+      if let Err(e) = hierarchy_util::delete_hierarchy(root, data.world) {
+        warn!("Failed to delete loading ui: {:?}", e);
+      }
+    }
+    self.ui_root = None;
+  }
+
+  fn update(&mut self, data: &mut StateData<GameData>) -> SimpleTrans {
+    match self.progress.poll(&mut data.world) {
+      LoadingStatus::Loading => return Trans::None,
+      LoadingStatus::Failed(failures) => return Trans::Switch(Box::new(ErrorState::new(failures))),
+      LoadingStatus::Done => {}
+    }
+
+    info!("level {} loaded", self.level.id());
+    let problems = register_named_aliases::<LevelPrefabData>(&mut data.world);
+    if !problems.is_empty() {
+      for problem in problems.iter() {
+        error!("{}", problem);
+      }
+      let messages = problems.iter().map(|p| p.to_string()).collect();
+      return Trans::Switch(Box::new(ErrorState::new(messages)));
+    }
+    Trans::Switch(Box::new(MainGameState::new(self.level.clone())))
+  }
+}
@@ -1,27 +1,31 @@
 use crate::{
-  resources::prefabs::{initialize_prefabs, update_prefab_names},
-  states::game::MainGameState,
+  resources::prefabs::{initialize_prefabs, register_prefab_aliases, PrefabConfig},
+  states::{error::ErrorState, menu::MenuState},
   utils::hierarchy_util,
 };
 use amethyst::{
-  assets::ProgressCounter, audio::output::init_output, ecs::Entity, prelude::*, ui::UiCreator,
+  assets::ProgressCounter,
+  audio::output::init_output,
+  ecs::{Entity, WriteStorage},
+  prelude::*,
+  ui::{UiCreator, UiFinder, UiText, UiTransform},
 };
-use log::info;
+use log::{error, info, warn};
+
+const PROGRESS_BAR_WIDTH: f32 = 300.;
 
 pub struct LoadingState {
   scene_root: Option<Entity>,
   ui_root: Option<Entity>,
-  counter: i32,
-  loading_progress: Option<ProgressCounter>,
+  progress: LoadingProgress,
 }
 
 impl Default for LoadingState {
   fn default() -> Self {
     Self {
       scene_root: None,
-      loading_progress: None,
       ui_root: None,
-      counter: 0,
+      progress: LoadingProgress::default(),
     }
   }
 }
@@ -37,16 +41,26 @@ impl SimpleState for LoadingState {
     // start loading all the things
     init_output(&mut world);
 
-    self.loading_progress = Some(initialize_prefabs(&mut world));
+    match initialize_prefabs(&mut world) {
+      Ok(counter) => self.progress.start(counter),
+      Err(e) => {
+        error!("Failed to start loading: {}", e);
+        self.progress.fail(e.to_string());
+      }
+    }
   }
 
   fn on_stop(&mut self, data: StateData<GameData>) {
     // delete the loader ui and scene
     if let Some(root) = self.ui_root {
-      hierarchy_util::delete_hierarchy(root, data.world).expect("failed to delete loading ui");
+      if let Err(e) = hierarchy_util::delete_hierarchy(root, data.world) {
+        warn!("Failed to delete loading ui: {:?}", e);
+      }
     }
     if let Some(root) = self.scene_root {
-      hierarchy_util::delete_hierarchy(root, data.world).expect("failed to delete loader scene");
+      if let Err(e) = hierarchy_util::delete_hierarchy(root, data.world) {
+        warn!("Failed to delete loader scene: {:?}", e);
+      }
     }
 
     self.ui_root = None;
@@ -54,21 +68,110 @@ impl SimpleState for LoadingState {
   }
 
   fn update(&mut self, data: &mut StateData<GameData>) -> SimpleTrans {
-    data.data.update(&data.world);
-    self.counter += 1;
-
-    if let Some(counter) = &self.loading_progress {
-      if counter.is_complete() && self.counter >= 100 {
-        info!("counter complete!");
-        self.loading_progress = None;
-        update_prefab_names(&mut data.world);
-        return Trans::Switch(Box::new(MainGameState::default()));
-      } else if counter.num_failed() > 0 {
-        //info!("some assets failed loading {}", counter.num_failed());
-      } else if counter.num_loading() > 0 {
-        //info!("still loading {} assets", counter.num_loading());
+    match self.progress.poll(&mut data.world) {
+      LoadingStatus::Loading => return Trans::None,
+      LoadingStatus::Failed(failures) => return Trans::Switch(Box::new(ErrorState::new(failures))),
+      LoadingStatus::Done => {}
+    }
+
+    info!("counter complete!");
+    let mut problems = register_prefab_aliases(&mut data.world);
+    problems.extend(data.world.read_resource::<PrefabConfig>().validate(&data.world));
+    if !problems.is_empty() {
+      for problem in problems.iter() {
+        error!("{}", problem);
+      }
+      let messages = problems.iter().map(|p| p.to_string()).collect();
+      return Trans::Switch(Box::new(ErrorState::new(messages)));
+    }
+    Trans::Switch(Box::new(MenuState::default()))
+  }
+}
+
+// what a loading state should do after polling its progress
+pub enum LoadingStatus {
+  Loading,
+  Failed(Vec<String>),
+  Done,
+}
+
+// the progress behind a loading screen, shared by the startup and level loading states
+#[derive(Default)]
+pub struct LoadingProgress {
+  counter: Option<ProgressCounter>,
+  failures: Vec<String>, // loading couldn't start
+}
+
+impl LoadingProgress {
+  pub fn start(&mut self, counter: ProgressCounter) {
+    self.counter = Some(counter);
+  }
+
+  pub fn fail(&mut self, failure: String) {
+    self.failures.push(failure);
+  }
+
+  // updates the loader ui and reports failures once everything has finished loading
+  pub fn poll(&mut self, world: &mut World) -> LoadingStatus {
+    if !self.failures.is_empty() {
+      return LoadingStatus::Failed(std::mem::replace(&mut self.failures, Vec::new()));
+    }
+    let counter = match &self.counter {
+      Some(counter) => counter,
+      None => return LoadingStatus::Loading,
+    };
+
+    let failures = counter
+      .errors()
+      .into_iter()
+      .map(|e| format!("{}: {}", e.asset_name, e.error))
+      .collect::<Vec<_>>();
+    show_progress(world, counter, &failures);
+
+    if counter.num_loading() > 0 {
+      return LoadingStatus::Loading;
+    }
+    self.counter = None;
+
+    if !failures.is_empty() {
+      for failure in failures.iter() {
+        error!("Failed to load {}", failure);
       }
+      return LoadingStatus::Failed(failures);
     }
-    Trans::None
+    LoadingStatus::Done
   }
 }
+
+// the loader ui may not be created yet, in which case nothing is shown
+fn show_progress(world: &mut World, counter: &ProgressCounter, failures: &[String]) {
+  let done = counter.num_finished() + counter.num_failed();
+  let fraction = if counter.num_assets() == 0 {
+    1.
+  } else {
+    done as f32 / counter.num_assets() as f32
+  };
+
+  // the finder reads the ui transforms, so look everything up before writing
+  let (fill, progress_text, errors_text) = world.exec(|finder: UiFinder<'_>| {
+    (
+      finder.find("loading_bar_fill"),
+      finder.find("loading_text"),
+      finder.find("loading_errors"),
+    )
+  });
+
+  world.exec(
+    |(mut transforms, mut texts): (WriteStorage<'_, UiTransform>, WriteStorage<'_, UiText>)| {
+      if let Some(fill) = fill.and_then(|e| transforms.get_mut(e)) {
+        fill.width = PROGRESS_BAR_WIDTH * fraction;
+      }
+      if let Some(text) = progress_text.and_then(|e| texts.get_mut(e)) {
+        text.text = format!("Loading... {}/{}", done, counter.num_assets());
+      }
+      if let Some(text) = errors_text.and_then(|e| texts.get_mut(e)) {
+        text.text = failures.join("\n");
+      }
+    },
+  );
+}
@@ -1,9 +1,18 @@
-use crate::{resources::prefabs::PrefabRegistry, utils::hierarchy_util};
-use amethyst::{ecs::Entity, prelude::*, ui::UiPrefab};
+use crate::{
+  resources::prefabs::{PrefabConfig, PrefabRegistry},
+  states::{error::ErrorState, level_loading::LevelLoadingState},
+  utils::{errors::GameError, hierarchy_util},
+};
+use amethyst::{
+  ecs::Entity,
+  prelude::*,
+  ui::{UiEvent, UiEventType, UiFinder, UiPrefab},
+};
 
 pub struct MenuState {
   scene_root: Option<Entity>,
   ui_root: Option<Entity>,
+  error: Option<GameError>, // reported by switching to the error state
 }
 
 impl Default for MenuState {
@@ -11,6 +20,7 @@ impl Default for MenuState {
     Self {
       scene_root: None,
       ui_root: None,
+      error: None,
     }
   }
 }
@@ -20,23 +30,63 @@ impl SimpleState for MenuState {
     let menu_prefab = data
       .world
       .read_resource::<PrefabRegistry<UiPrefab>>()
-      .get_prefab("main_menu") // todo: move ids to config file
-      .expect("main menu prefab not found")
-      .clone();
+      .require("main_menu", &data.world.read_resource::<PrefabConfig>().main_menu);
 
-    self.ui_root = Some(data.world.create_entity().with(menu_prefab.clone()).build());
+    match menu_prefab {
+      Ok(menu_prefab) => {
+        self.ui_root = Some(data.world.create_entity().with(menu_prefab).build());
+      }
+      // validated by the loading state, so this only happens if the registry changed since
+      Err(e) => self.error = Some(e),
+    }
   }
 
   fn on_stop(&mut self, data: StateData<GameData>) {
     // delete the ui and scene
     if let Some(root) = self.ui_root {
-      hierarchy_util::delete_hierarchy(root, data.world).expect("failed to delete loading ui");
+      if let Err(e) = hierarchy_util::delete_hierarchy(root, data.world) {
+        log::warn!("Failed to delete menu ui: {:?}", e);
+      }
     }
     if let Some(root) = self.scene_root {
-      hierarchy_util::delete_hierarchy(root, data.world).expect("failed to delete loader scene");
+      if let Err(e) = hierarchy_util::delete_hierarchy(root, data.world) {
+        log::warn!("Failed to delete menu scene: {:?}", e);
+      }
     }
 
     self.ui_root = None;
     self.scene_root = None;
   }
+
+  fn update(&mut self, _: &mut StateData<GameData>) -> SimpleTrans {
+    match self.error.take() {
+      Some(e) => {
+        log::error!("{}", e);
+        Trans::Switch(Box::new(ErrorState::new(vec![e.to_string()])))
+      }
+      None => Trans::None,
+    }
+  }
+
+  fn handle_event(&mut self, data: StateData<GameData>, event: StateEvent) -> SimpleTrans {
+    let target = match event {
+      StateEvent::Ui(UiEvent {
+        event_type: UiEventType::Click,
+        target,
+      }) => target,
+      _ => return Trans::None,
+    };
+
+    let (start, exit) = data.world.exec(|finder: UiFinder<'_>| {
+      (finder.find("btn_start_game"), finder.find("btn_exit_game"))
+    });
+    if Some(target) == start {
+      let level = data.world.read_resource::<PrefabConfig>().level.clone();
+      Trans::Switch(Box::new(LevelLoadingState::new(level)))
+    } else if Some(target) == exit {
+      Trans::Quit
+    } else {
+      Trans::None
+    }
+  }
 }
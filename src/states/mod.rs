@@ -1,3 +1,5 @@
+pub mod error;
 pub mod game;
+pub mod level_loading;
 pub mod loading;
 pub mod menu;
@@ -1,3 +1,4 @@
+use crate::systems::terrain::PropStats;
 use amethyst::{
     core::{SystemDesc, Time},
     derive::SystemDesc,
@@ -10,6 +11,8 @@ use amethyst::{
 pub struct DebugSystem {
     #[system_desc(skip)]
     fps_display: Option<Entity>,
+    #[system_desc(skip)]
+    draw_calls_display: Option<Entity>,
 }
 
 impl<'a> System<'a> for DebugSystem {
@@ -17,17 +20,23 @@ impl<'a> System<'a> for DebugSystem {
         Read<'a, Time>,
         WriteStorage<'a, UiText>,
         Read<'a, FpsCounter>,
+        Read<'a, PropStats>,
         UiFinder<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (time, mut ui_text, fps_counter, finder) = data;
+        let (time, mut ui_text, fps_counter, prop_stats, finder) = data;
 
         if self.fps_display.is_none() {
             if let Some(fps_entity) = finder.find("fps_text") {
                 self.fps_display = Some(fps_entity);
             }
         }
+        if self.draw_calls_display.is_none() {
+            if let Some(draw_calls_entity) = finder.find("draw_calls_text") {
+                self.draw_calls_display = Some(draw_calls_entity);
+            }
+        }
         if let Some(fps_entity) = self.fps_display {
             if let Some(fps_display) = ui_text.get_mut(fps_entity) {
                 if time.frame_number() % 20 == 0 {
@@ -36,5 +45,18 @@ impl<'a> System<'a> for DebugSystem {
                 }
             }
         }
+        if let Some(draw_calls_entity) = self.draw_calls_display {
+            if let Some(draw_calls_display) = ui_text.get_mut(draw_calls_entity) {
+                if time.frame_number() % 20 == 0 {
+                    draw_calls_display.text = format!(
+                        "props: {} draw calls ({}/{} batches, {} instances)",
+                        prop_stats.draw_calls(),
+                        prop_stats.visible_batches,
+                        prop_stats.batches,
+                        prop_stats.instances
+                    );
+                }
+            }
+        }
     }
 }
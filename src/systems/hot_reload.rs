@@ -0,0 +1,201 @@
+use crate::{
+    components::terrain::{Chunk, ChunkWater, VoxelData},
+    resources::{
+        chunk_map::ChunkMap,
+        prefabs::{finish_prefab_reloads, reload_prefab},
+        terrain::HeightGenerator,
+    },
+    systems::terrain::{
+        generate_voxels, unload_chunk, ChunkEvent, ChunkEventKind, ScatterConfig, TerrainSettings,
+    },
+    utils::errors::HeightmapError,
+};
+use amethyst::{
+    config::Config,
+    core::SystemBundle,
+    ecs::{prelude::*, LazyUpdate},
+    shrev::EventChannel,
+    utils::application_root_dir,
+    Error,
+};
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+// watches the assets and config folders and reloads what changed (`dev` feature only)
+pub struct HotReloadBundle {
+    assets_dir: PathBuf,
+    config_dir: PathBuf,
+}
+
+impl HotReloadBundle {
+    pub fn new(assets_dir: PathBuf, config_dir: PathBuf) -> Self {
+        Self {
+            assets_dir,
+            config_dir,
+        }
+    }
+}
+
+impl<'a, 'b> SystemBundle<'a, 'b> for HotReloadBundle {
+    fn build(
+        self,
+        _world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        let (sender, events) = channel();
+        let mut file_watcher = watcher(sender, Duration::from_millis(200)).map_err(Error::new)?;
+        file_watcher
+            .watch(&self.assets_dir, RecursiveMode::Recursive)
+            .map_err(Error::new)?;
+        file_watcher
+            .watch(&self.config_dir, RecursiveMode::Recursive)
+            .map_err(Error::new)?;
+
+        builder.add(
+            HotReloadSystem {
+                _watcher: file_watcher,
+                events,
+                assets_dir: self.assets_dir,
+                config_dir: self.config_dir,
+            },
+            "hot_reload",
+            &[],
+        );
+        Ok(())
+    }
+}
+
+// the changes are applied through `LazyUpdate` as reloading needs the whole world
+pub struct HotReloadSystem {
+    _watcher: RecommendedWatcher, // stops watching when dropped
+    events: Receiver<DebouncedEvent>,
+    assets_dir: PathBuf,
+    config_dir: PathBuf,
+}
+
+impl<'a> System<'a> for HotReloadSystem {
+    type SystemData = Read<'a, LazyUpdate>;
+
+    fn run(&mut self, lazy: Self::SystemData) {
+        let mut changed = self
+            .events
+            .try_iter()
+            .filter_map(|event| match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Rename(_, path) => Some(path),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        changed.sort();
+        changed.dedup();
+
+        for path in changed {
+            if let Ok(relative) = path.strip_prefix(&self.assets_dir) {
+                let relative = relative.to_string_lossy().replace('\\', "/");
+                lazy.exec_mut(move |world| {
+                    if !reload_prefab(world, &relative) {
+                        log::info!(
+                            "{} changed but is not in the asset manifest, run generate-manifest",
+                            relative
+                        );
+                    }
+                });
+            } else if path == self.config_dir.join("scatter.ron") {
+                lazy.exec_mut(move |world| reload_scatter_config(world, &path));
+            } else if path == self.config_dir.join("terrain.ron") {
+                lazy.exec_mut(move |world| reload_terrain_settings(world, &path));
+            }
+        }
+
+        lazy.exec_mut(finish_prefab_reloads);
+    }
+}
+
+// loaded chunks are unloaded so they are generated again with the new props
+fn reload_scatter_config(world: &mut World, path: &Path) {
+    log::info!("Reloading {:?}, regenerating chunks", path);
+    world.insert(ScatterConfig::load(path));
+    unload_chunks(world);
+}
+
+// loaded chunks get new voxels in place, everything built from them is rebuilt on `Modified`.
+// a new chunk or voxel size moves every chunk, so they are unloaded instead
+fn reload_terrain_settings(world: &mut World, path: &Path) {
+    let settings = TerrainSettings::load(path);
+    // the heightmap may have changed too, a broken one keeps the current heights
+    let seed = world.read_resource::<HeightGenerator>().seed();
+    let generator = application_root_dir()
+        .map_err(HeightmapError::from)
+        .and_then(|root| HeightGenerator::from_settings(seed, &settings, &root));
+    match generator {
+        Ok(generator) => world.insert(generator),
+        Err(e) => log::error!("Failed to load the terrain heightmap: {}", e),
+    }
+
+    let resized = {
+        let current = world.read_resource::<TerrainSettings>();
+        current.chunk_size != settings.chunk_size || current.voxel_size != settings.voxel_size
+    };
+    world.insert(settings);
+
+    if resized {
+        log::info!(
+            "Reloading {:?}, chunk size changed, regenerating chunks",
+            path
+        );
+        unload_chunks(world);
+        return;
+    }
+
+    log::info!("Reloading {:?}, regenerating chunk heights", path);
+    world.exec(
+        |(settings, generator, entities, chunks, mut voxel_data, mut events): (
+            Read<'_, TerrainSettings>,
+            ReadExpect<'_, HeightGenerator>,
+            Entities<'_>,
+            ReadStorage<'_, Chunk>,
+            WriteStorage<'_, VoxelData>,
+            Write<'_, EventChannel<ChunkEvent>>,
+        )| {
+            // chunks without voxels yet are generated with the new settings anyway
+            let generated = (&entities, &chunks, &voxel_data)
+                .join()
+                .map(|(entity, chunk, _)| (entity, chunk.coord))
+                .collect::<Vec<_>>();
+
+            for (entity, coord) in generated {
+                voxel_data
+                    .insert(entity, generate_voxels(&settings, &generator, coord))
+                    .expect("voxel data insert failed");
+                events.single_write(ChunkEvent::new(ChunkEventKind::Modified, coord, entity));
+            }
+        },
+    );
+}
+
+fn unload_chunks(world: &mut World) {
+    world.exec(
+        |(mut chunk_map, waters, entities, mut events): (
+            Write<'_, ChunkMap>,
+            ReadStorage<'_, ChunkWater>,
+            Entities<'_>,
+            Write<'_, EventChannel<ChunkEvent>>,
+        )| {
+            for (coord, entity) in chunk_map.iter().collect::<Vec<_>>() {
+                unload_chunk(
+                    coord,
+                    entity,
+                    &mut chunk_map,
+                    &waters,
+                    &entities,
+                    &mut events,
+                );
+            }
+        },
+    );
+}
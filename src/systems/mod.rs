@@ -1,3 +1,7 @@
 pub mod debug;
+#[cfg(feature = "dev")]
+pub mod hot_reload;
+pub mod physics;
 pub mod terrain;
 pub mod ui_event_handler;
+pub mod walk_controller;
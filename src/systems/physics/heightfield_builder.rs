@@ -0,0 +1,63 @@
+use crate::{
+    components::{physics::Heightfield, terrain::VoxelData},
+    systems::terrain::{ChunkEvent, ChunkEventKind, TerrainSettings},
+    utils::coords::VoxelCoord,
+};
+use amethyst::{
+    ecs::prelude::*,
+    shrev::{EventChannel, ReaderId},
+};
+
+// builds a heightfield collider for every chunk once its voxels are generated
+#[derive(Default)]
+pub struct HeightfieldBuilderSystem {
+    reader_id: Option<ReaderId<ChunkEvent>>,
+}
+
+impl<'a> System<'a> for HeightfieldBuilderSystem {
+    type SystemData = (
+        Read<'a, TerrainSettings>,
+        Read<'a, EventChannel<ChunkEvent>>,
+        Entities<'a>,
+        ReadStorage<'a, VoxelData>,
+        WriteStorage<'a, Heightfield>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(
+            world
+                .fetch_mut::<EventChannel<ChunkEvent>>()
+                .register_reader(),
+        );
+    }
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (settings, events, entities, voxel_data, mut heightfields) = data;
+
+        let reader_id = self.reader_id.as_mut().expect("setup was not called");
+
+        for event in events.read(reader_id) {
+            match event.kind {
+                ChunkEventKind::VoxelsReady | ChunkEventKind::Modified => {}
+                _ => continue,
+            }
+            if !entities.is_alive(event.entity) {
+                continue;
+            }
+            let voxels = match voxel_data.get(event.entity) {
+                Some(voxels) => voxels,
+                None => continue,
+            };
+
+            let origin = VoxelCoord::from_local(event.coord, 0, 0, &settings).center(&settings);
+            let heightfield = Heightfield {
+                origin: (origin.x, origin.z),
+                cell_size: settings.voxel_size,
+                size: settings.chunk_size as usize,
+                heights: voxels.voxels.iter().map(|v| v.heights[0]).collect(),
+            };
+            heightfields.insert(event.entity, heightfield).unwrap();
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use amethyst::{
+    core::{ecs::prelude::*, math::Vector3, SystemBundle},
+    Error,
+};
+
+mod heightfield_builder;
+mod step;
+
+pub use heightfield_builder::HeightfieldBuilderSystem;
+pub use step::PhysicsStepSystem;
+
+pub struct PhysicsSettings {
+    pub gravity: Vector3<f32>,
+    pub timestep: f32,    // length of a fixed simulation step in seconds
+    pub max_steps: usize, // steps per frame, the simulation slows down rather than spiral
+}
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            gravity: Vector3::new(0., -60., 0.),
+            timestep: 1. / 60.,
+            max_steps: 4,
+        }
+    }
+}
+
+// rigid bodies colliding with each other and the terrain, needs the terrain bundle
+#[derive(Default)]
+pub struct PhysicsBundle;
+
+impl<'a, 'b> SystemBundle<'a, 'b> for PhysicsBundle {
+    fn build(
+        self,
+        world: &mut World,
+        builder: &mut DispatcherBuilder<'a, 'b>,
+    ) -> Result<(), Error> {
+        world.insert(PhysicsSettings::default());
+        builder.add(
+            HeightfieldBuilderSystem::default(),
+            "physics_heightfield_builder",
+            &["terrain_voxel_generator"],
+        );
+        builder.add(
+            PhysicsStepSystem::default(),
+            "physics_step",
+            &["physics_heightfield_builder"],
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,382 @@
+use super::PhysicsSettings;
+use crate::{
+    components::physics::{Collider, Heightfield, RigidBody},
+    resources::chunk_map::ChunkMap,
+    systems::terrain::TerrainSettings,
+    utils::coords::{ChunkCoord, WorldPos},
+};
+use amethyst::{
+    core::{
+        math::{Matrix4, Point3, Vector3},
+        Parent, Time, Transform,
+    },
+    ecs::prelude::*,
+};
+
+struct Body {
+    entity: Entity,
+    to_world: Matrix4<f32>, // the parent's world matrix, identity without a parent
+    position: Vector3<f32>, // in world space
+    velocity: Vector3<f32>,
+    inverse_mass: f32,
+    gravity_scale: f32,
+    damping: f32,
+    collider: Option<Collider>,
+}
+
+// advances rigid bodies in fixed steps, colliding them with each other and the terrain
+// bodies standing on chunks without a heightfield yet are frozen so they don't fall through
+#[derive(Default)]
+pub struct PhysicsStepSystem {
+    accumulator: f32,
+}
+
+impl<'a> System<'a> for PhysicsStepSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, PhysicsSettings>,
+        Read<'a, TerrainSettings>,
+        Read<'a, ChunkMap>,
+        Entities<'a>,
+        ReadStorage<'a, Heightfield>,
+        ReadStorage<'a, Collider>,
+        ReadStorage<'a, Parent>,
+        WriteStorage<'a, RigidBody>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            time,
+            settings,
+            terrain_settings,
+            chunk_map,
+            entities,
+            heightfields,
+            colliders,
+            parents,
+            mut rigid_bodies,
+            mut transforms,
+        ) = data;
+
+        self.accumulator += time.delta_seconds();
+        let mut steps = 0;
+        while self.accumulator >= settings.timestep && steps < settings.max_steps {
+            self.accumulator -= settings.timestep;
+            steps += 1;
+        }
+        if steps == 0 {
+            return;
+        }
+        // drop the time we couldn't catch up with
+        self.accumulator = self.accumulator.min(settings.timestep);
+
+        // world positions go through the parent's matrix rather than the body's own,
+        // which isn't computed yet for bodies spawned this frame
+        let mut bodies = (&*entities, &rigid_bodies, &transforms)
+            .join()
+            .map(|(entity, body, transform)| {
+                let to_world = parents
+                    .get(entity)
+                    .and_then(|parent| transforms.get(parent.entity))
+                    .map_or_else(Matrix4::identity, |parent| *parent.global_matrix());
+                Body {
+                    entity,
+                    to_world,
+                    position: to_world
+                        .transform_point(&Point3::from(*transform.translation()))
+                        .coords,
+                    velocity: body.velocity,
+                    inverse_mass: body.inverse_mass(),
+                    gravity_scale: body.gravity_scale,
+                    damping: body.linear_damping,
+                    collider: colliders.get(entity).cloned(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let heightfield_at = |position: &Vector3<f32>| {
+            let coord = ChunkCoord::from_world(WorldPos::from(position), &terrain_settings);
+            chunk_map
+                .get(coord)
+                .and_then(|entity| heightfields.get(entity))
+        };
+
+        let dt = settings.timestep;
+        for _ in 0..steps {
+            for body in bodies.iter_mut().filter(|b| b.inverse_mass > 0.) {
+                if heightfield_at(&body.position).is_none() {
+                    continue;
+                }
+                body.velocity += settings.gravity * body.gravity_scale * dt;
+                body.velocity *= (1. - body.damping * dt).max(0.);
+                body.position += body.velocity * dt;
+            }
+
+            resolve_bodies(&mut bodies);
+
+            for body in bodies.iter_mut().filter(|b| b.inverse_mass > 0.) {
+                if let Some(heightfield) = heightfield_at(&body.position) {
+                    resolve_ground(body, heightfield);
+                }
+            }
+        }
+
+        for body in bodies {
+            if let Some(rigid_body) = rigid_bodies.get_mut(body.entity) {
+                rigid_body.velocity = body.velocity;
+            }
+            if body.inverse_mass > 0. {
+                let local = body
+                    .to_world
+                    .try_inverse()
+                    .map(|to_local| to_local.transform_point(&Point3::from(body.position)));
+                if let (Some(local), Some(transform)) = (local, transforms.get_mut(body.entity)) {
+                    transform.set_translation(local.coords);
+                }
+            }
+        }
+    }
+}
+
+// pushes overlapping bodies apart, shapes are approximated by their bounding spheres
+fn resolve_bodies(bodies: &mut [Body]) {
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            let (left, right) = bodies.split_at_mut(j);
+            let (a, b) = (&mut left[i], &mut right[0]);
+            let total_inverse_mass = a.inverse_mass + b.inverse_mass;
+            if total_inverse_mass == 0. {
+                continue;
+            }
+            let (ca, cb) = match (a.collider.as_ref(), b.collider.as_ref()) {
+                (Some(ca), Some(cb)) => (ca, cb),
+                _ => continue,
+            };
+
+            let delta = b.position - a.position;
+            let distance = delta.norm();
+            let penetration = ca.shape.bounding_radius() + cb.shape.bounding_radius() - distance;
+            if penetration <= 0. {
+                continue;
+            }
+            let normal = if distance > std::f32::EPSILON {
+                delta / distance
+            } else {
+                Vector3::y()
+            };
+
+            a.position -= normal * penetration * a.inverse_mass / total_inverse_mass;
+            b.position += normal * penetration * b.inverse_mass / total_inverse_mass;
+
+            let closing = (b.velocity - a.velocity).dot(&normal);
+            if closing < 0. {
+                let restitution = ca.restitution.min(cb.restitution);
+                let impulse = -(1. + restitution) * closing / total_inverse_mass;
+                a.velocity -= normal * impulse * a.inverse_mass;
+                b.velocity += normal * impulse * b.inverse_mass;
+            }
+        }
+    }
+}
+
+// bodies without a collider are kept above the ground as a point that doesn't bounce
+fn resolve_ground(body: &mut Body, heightfield: &Heightfield) {
+    let (half_height, restitution, friction) = match &body.collider {
+        Some(collider) => (
+            collider.shape.half_height(),
+            collider.restitution,
+            collider.friction,
+        ),
+        None => (0., 0., 0.),
+    };
+    let ground = heightfield.height_at(body.position.x, body.position.z);
+    let penetration = ground - (body.position.y - half_height);
+    if penetration <= 0. {
+        return;
+    }
+    body.position.y += penetration;
+
+    let normal = heightfield.normal_at(body.position.x, body.position.z);
+    let approach = body.velocity.dot(&normal);
+    if approach >= 0. {
+        return;
+    }
+    let impulse = -(1. + restitution) * approach;
+    body.velocity += normal * impulse;
+
+    // coulomb friction, the tangential speed is reduced by at most friction * normal impulse
+    let tangent = body.velocity - normal * body.velocity.dot(&normal);
+    let speed = tangent.norm();
+    if speed > std::f32::EPSILON {
+        let reduction = (friction * impulse).min(speed);
+        body.velocity -= tangent / speed * reduction;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::physics::ColliderShape;
+
+    const STEP: f32 = 1. / 60.;
+
+    fn world() -> World {
+        let mut world = World::new();
+        world.register::<Heightfield>();
+        world.register::<Collider>();
+        world.register::<Parent>();
+        world.register::<RigidBody>();
+        world.register::<Transform>();
+        world.insert(Time::default());
+        world.insert(PhysicsSettings {
+            gravity: Vector3::new(0., -10., 0.),
+            timestep: STEP,
+            max_steps: 4,
+        });
+        world.insert(TerrainSettings {
+            chunk_size: 4,
+            voxel_size: 2.,
+            ..TerrainSettings::default()
+        });
+        world.insert(ChunkMap::default());
+        world
+    }
+
+    // flat ground at `height` under chunk (0, 0)
+    fn load_ground(world: &mut World, height: f32) {
+        let entity = world
+            .create_entity()
+            .with(Heightfield {
+                origin: (-3., -3.),
+                cell_size: 2.,
+                size: 4,
+                heights: vec![height; 16],
+            })
+            .build();
+        world
+            .write_resource::<ChunkMap>()
+            .insert(ChunkCoord(0, 0), entity);
+    }
+
+    fn body(world: &mut World, x: f32, y: f32, collider: Option<ColliderShape>) -> Entity {
+        let mut builder = world
+            .create_entity()
+            .with(RigidBody {
+                linear_damping: 0.,
+                ..RigidBody::default()
+            })
+            .with(Transform::from(Vector3::new(x, y, 0.)));
+        if let Some(shape) = collider {
+            builder = builder.with(Collider {
+                shape,
+                restitution: 0.,
+                friction: 0.5,
+            });
+        }
+        builder.build()
+    }
+
+    // runs `frames` frames of `steps` fixed steps each
+    fn run(world: &mut World, frames: usize, steps: usize) {
+        let mut system = PhysicsStepSystem::default();
+        for _ in 0..frames {
+            world
+                .write_resource::<Time>()
+                .set_delta_seconds(STEP * steps as f32 + 1e-4);
+            system.run_now(world);
+            world.maintain();
+        }
+    }
+
+    fn height(world: &World, entity: Entity) -> f32 {
+        world
+            .read_storage::<Transform>()
+            .get(entity)
+            .unwrap()
+            .translation()
+            .y
+    }
+
+    fn velocity(world: &World, entity: Entity) -> Vector3<f32> {
+        world
+            .read_storage::<RigidBody>()
+            .get(entity)
+            .unwrap()
+            .velocity
+    }
+
+    #[test]
+    fn falls_with_semi_implicit_euler() {
+        let mut world = world();
+        load_ground(&mut world, -1000.);
+        let ball = body(&mut world, 0., 0., Some(ColliderShape::Ball { radius: 1. }));
+
+        run(&mut world, 1, 3);
+
+        // v_n = -g * n * dt, y_n = -g * dt^2 * (1 + 2 + .. + n)
+        assert!((velocity(&world, ball).y + 10. * 3. * STEP).abs() < 1e-4);
+        assert!((height(&world, ball) + 10. * STEP * STEP * 6.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn frames_are_capped_at_max_steps() {
+        let mut world = world();
+        load_ground(&mut world, -1000.);
+        let ball = body(&mut world, 0., 0., Some(ColliderShape::Ball { radius: 1. }));
+
+        run(&mut world, 1, 10);
+
+        assert!((velocity(&world, ball).y + 10. * 4. * STEP).abs() < 1e-4);
+    }
+
+    #[test]
+    fn bodies_without_a_heightfield_are_frozen() {
+        let mut world = world();
+        let ball = body(&mut world, 0., 5., Some(ColliderShape::Ball { radius: 1. }));
+
+        run(&mut world, 10, 1);
+
+        assert_eq!(height(&world, ball), 5.);
+        assert_eq!(velocity(&world, ball), Vector3::zeros());
+    }
+
+    #[test]
+    fn colliders_rest_on_the_ground() {
+        let mut world = world();
+        load_ground(&mut world, 2.);
+        let ball = body(
+            &mut world,
+            -2.,
+            10.,
+            Some(ColliderShape::Ball { radius: 1. }),
+        );
+        let capsule = body(
+            &mut world,
+            2.,
+            10.,
+            Some(ColliderShape::Capsule {
+                half_height: 1.,
+                radius: 0.5,
+            }),
+        );
+
+        run(&mut world, 120, 1);
+
+        assert!((height(&world, ball) - 3.).abs() < 1e-3);
+        assert!((height(&world, capsule) - 3.5).abs() < 1e-3);
+        assert!(velocity(&world, ball).y.abs() < 1e-3);
+    }
+
+    #[test]
+    fn bodies_without_a_collider_stop_at_the_ground() {
+        let mut world = world();
+        load_ground(&mut world, 2.);
+        let point = body(&mut world, 0., 10., None);
+
+        run(&mut world, 120, 1);
+
+        assert!((height(&world, point) - 2.).abs() < 1e-3);
+        assert!(velocity(&world, point).y.abs() < 1e-3);
+    }
+}
@@ -1,4 +1,5 @@
-use crate::components::terrain::{Chunk, VoxelData};
+use super::{ChunkEvent, ChunkEventKind};
+use crate::components::terrain::{Chunk, ChunkBounds, Occluded, VoxelData};
 
 use amethyst::{
     assets::{AssetLoaderSystemData, Handle},
@@ -7,11 +8,14 @@ use amethyst::{
     derive::SystemDesc,
     ecs::prelude::*,
     renderer::{types::Mesh, visibility::BoundingSphere, Material},
+    shrev::{EventChannel, ReaderId},
 };
 
 // generates meshes for chunks
 #[derive(Default)]
-pub struct ChunkMeshBuilderSystem;
+pub struct ChunkMeshBuilderSystem {
+    reader_id: Option<ReaderId<ChunkEvent>>,
+}
 
 impl<'a> System<'a> for ChunkMeshBuilderSystem {
     type SystemData = (
@@ -19,44 +23,79 @@ impl<'a> System<'a> for ChunkMeshBuilderSystem {
         Entities<'a>,
         ReadStorage<'a, Chunk>,
         ReadStorage<'a, VoxelData>, // convert to read id
+        ReadStorage<'a, Occluded>,
         AssetLoaderSystemData<'a, Mesh>,
         WriteStorage<'a, Handle<Mesh>>,
         WriteStorage<'a, Handle<Material>>,
         WriteStorage<'a, Transform>,
         WriteStorage<'a, BoundingSphere>,
+        WriteStorage<'a, ChunkBounds>,
         Read<'a, crate::states::game::Hax>,
+        Write<'a, EventChannel<ChunkEvent>>,
     );
 
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(
+            world
+                .fetch_mut::<EventChannel<ChunkEvent>>()
+                .register_reader(),
+        );
+    }
+
     fn run(&mut self, data: Self::SystemData) {
         let (
             settings,
             entities,
             chunks,
             voxel_data,
+            occluded,
             mesh_loader,
             mut meshes,
             mut materials,
             mut transforms,
             mut bounds,
+            mut chunk_bounds,
             hax,
+            mut events,
         ) = data;
 
+        let reader_id = self.reader_id.as_mut().expect("setup was not called");
+        let mut modified = events
+            .read(reader_id)
+            .filter(|ev| ev.kind == ChunkEventKind::Modified)
+            .map(|ev| ev.entity)
+            .collect::<Vec<_>>();
+        modified.sort();
+        modified.dedup();
+
         if let Some(material) = hax.the_material.as_ref() {
-            let to_create = (&*entities, &chunks, &voxel_data, !&transforms)
+            // new chunks don't have a transform yet, modified ones need their mesh replaced
+            let to_create = (&*entities, &chunks, &voxel_data, !&transforms, !&occluded)
                 .join()
-                .map(|(entity, chunk, voxel, _)| (entity, chunk, voxel))
+                .map(|(entity, chunk, voxel, _, _)| (entity, chunk, voxel))
+                .chain(modified.into_iter().filter_map(|entity| {
+                    match (chunks.get(entity), voxel_data.get(entity)) {
+                        (Some(chunk), Some(voxel)) if transforms.contains(entity) => {
+                            Some((entity, chunk, voxel))
+                        }
+                        _ => None,
+                    }
+                }))
                 .collect::<Vec<_>>();
 
-            let chunk_size = settings.chunk_size as f32 * settings.voxel_size;
-            let offset = chunk_size / 2.;
+            let chunk_length = settings.chunk_length();
+            let offset = chunk_length / 2.;
 
             for (entity, chunk, voxel) in to_create.into_iter() {
                 log::info!("Creating mesh for {:?}", chunk);
-                let origin = Vector3::new(chunk.x, 0., chunk.y);
 
-                transforms
-                    .insert(entity, Transform::from(origin))
-                    .expect("transform insert failed");
+                if !transforms.contains(entity) {
+                    let origin = chunk.coord.center(&settings).to_vector(0.);
+                    transforms
+                        .insert(entity, Transform::from(origin))
+                        .expect("transform insert failed");
+                }
 
                 // todo: generate mesh based on LOD
                 meshes
@@ -78,12 +117,18 @@ impl<'a> System<'a> for ChunkMeshBuilderSystem {
                     .insert(entity, material.clone())
                     .expect("material insertion failed");
 
+                let aabb = ChunkBounds::from_voxels(voxel, chunk_length);
                 bounds
                     .insert(
                         entity,
-                        BoundingSphere::origin(((chunk_size * chunk_size) * 2.).sqrt() / 2.),
+                        BoundingSphere::new(Point3::from(aabb.center()), aabb.radius()),
                     )
                     .expect("bounding sphere insert failed");
+                chunk_bounds
+                    .insert(entity, aabb)
+                    .expect("chunk bounds insert failed");
+
+                events.single_write(ChunkEvent::new(ChunkEventKind::Meshed, chunk.coord, entity));
             }
         }
     }
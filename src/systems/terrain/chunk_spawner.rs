@@ -1,77 +1,124 @@
-use crate::components::terrain::Chunk;
+use super::{ChunkEvent, ChunkEventKind};
+use crate::{
+    components::terrain::Chunk, resources::chunk_map::ChunkMap, utils::coords::ChunkCoord,
+};
 
 use amethyst::{
     controls::FlyControlTag,
-    core::{SystemDesc, Transform},
+    core::{
+        math::{Vector2, Vector3},
+        SystemDesc, Transform,
+    },
     derive::SystemDesc,
     ecs::prelude::*,
+    renderer::camera::{Camera, Projection},
+    shrev::EventChannel,
 };
 
-// controls chunk lifetime
-#[derive(SystemDesc)]
-pub struct ChunkSpawnerSystem {
-    #[system_desc(skip)]
-    registry: std::collections::HashMap<(i32, i32), Entity>, // move to resource ?
-}
+// horizontal half angle used when the camera has no perspective projection
+const DEFAULT_HALF_FOV: f32 = std::f32::consts::FRAC_PI_4;
 
-impl Default for ChunkSpawnerSystem {
-    fn default() -> Self {
-        ChunkSpawnerSystem {
-            registry: std::collections::HashMap::new(),
-        }
-    }
-}
+// controls chunk lifetime
+// only a few chunks are created per frame, the ones in view and closest to the camera first
+#[derive(Default, SystemDesc)]
+pub struct ChunkSpawnerSystem;
 
 impl<'a> System<'a> for ChunkSpawnerSystem {
     type SystemData = (
         Read<'a, super::TerrainSettings>,
+        Write<'a, ChunkMap>,
         ReadStorage<'a, Transform>,
         ReadStorage<'a, FlyControlTag>,
+        ReadStorage<'a, Camera>,
         WriteStorage<'a, Chunk>,
         Entities<'a>,
+        Write<'a, EventChannel<ChunkEvent>>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (settings, transform, control_tag, mut chunks, entities) = data;
+        let (
+            settings,
+            mut chunk_map,
+            transform,
+            control_tag,
+            cameras,
+            mut chunks,
+            entities,
+            mut events,
+        ) = data;
 
         // find position of current camera (if no camera is found, then do nothing)
-        if let Some((position, _)) = (&transform, &control_tag).join().next() {
+        if let Some((eye, _, camera)) = (&transform, &control_tag, cameras.maybe()).join().next() {
             // find which chunk the camera is at
-            let translation = position.translation();
-
-            let chunk_size = settings.chunk_size as f32 * settings.voxel_size;
-            let half_chunk_size = chunk_size / 2.;
-            let x = ((translation.x + half_chunk_size) / chunk_size).floor() as i32;
-            let y = ((translation.z + half_chunk_size) / chunk_size).floor() as i32;
+            let current = ChunkCoord::from_world(eye.translation().into(), &settings);
+            let half_fov = camera.map_or(DEFAULT_HALF_FOV, horizontal_half_fov);
 
             // -- determine which chunks to create
-            let chunks_to_create = vec![
-                (x, y),
-                (x + 1, y),
-                (x - 1, y),
-                (x, y + 1),
-                (x, y - 1),
-                (x + 1, y + 1),
-                (x - 1, y - 1),
-                (x + 1, y - 1),
-                (x - 1, y + 1),
-            ];
+            let radius = settings.load_radius;
+            let mut chunks_to_create = (-radius..=radius)
+                .flat_map(|dy| (-radius..=radius).map(move |dx| current.offset(dx, dy)))
+                .filter(|coord| !chunk_map.contains(*coord))
+                .map(|coord| (priority(coord, eye, half_fov, &settings), coord))
+                .collect::<Vec<_>>();
+            chunks_to_create.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
 
             //create the chunks
-            for (x, y) in chunks_to_create.into_iter() {
-                if let None = self.registry.get(&(x, y)) {
-                    log::info!("Created chunk {:?}", (x, y));
-                    let entity = entities
-                        .build_entity()
-                        // todo: add lod component
-                        .with(
-                            Chunk::new(x as f32 * chunk_size, y as f32 * chunk_size),
-                            &mut chunks,
-                        )
-                        .build();
-                    self.registry.insert((x, y), entity);
-                }
+            for (_, coord) in chunks_to_create
+                .into_iter()
+                .take(settings.spawns_per_frame.max(1))
+            {
+                log::info!("Created chunk {:?}", coord);
+                let entity = entities
+                    .build_entity()
+                    // todo: add lod component
+                    .with(Chunk::new(coord), &mut chunks)
+                    .build();
+                chunk_map.insert(coord, entity);
+                events.single_write(ChunkEvent::new(ChunkEventKind::Spawned, coord, entity));
             }
         }
     }
 }
+
+fn horizontal_half_fov(camera: &Camera) -> f32 {
+    match camera.projection() {
+        Projection::Perspective(p) => ((p.fovy() / 2.).tan() * p.aspect()).atan(),
+        _ => DEFAULT_HALF_FOV,
+    }
+}
+
+// lower is sooner: the distance to the chunk, scaled up the further it is outside the view
+fn priority(
+    coord: ChunkCoord,
+    eye: &Transform,
+    half_fov: f32,
+    settings: &super::TerrainSettings,
+) -> f32 {
+    let center = coord.center(settings);
+    let to_chunk = Vector2::new(
+        center.x - eye.translation().x,
+        center.z - eye.translation().z,
+    );
+    let distance = to_chunk.norm();
+    let chunk_radius = settings.chunk_length() * std::f32::consts::FRAC_1_SQRT_2;
+    if distance <= chunk_radius {
+        return 0.;
+    }
+
+    let forward = eye.rotation() * -Vector3::z();
+    let forward = Vector2::new(forward.x, forward.z);
+    if forward.norm_squared() < std::f32::EPSILON {
+        // looking straight up or down, every direction is equally visible
+        return distance;
+    }
+
+    let angle = forward
+        .normalize()
+        .dot(&(to_chunk / distance))
+        .max(-1.)
+        .min(1.)
+        .acos();
+    let angular_radius = (chunk_radius / distance).asin();
+    let outside_view = (angle - half_fov - angular_radius).max(0.);
+    distance * (1. + 2. * outside_view)
+}
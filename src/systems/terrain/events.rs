@@ -0,0 +1,29 @@
+use crate::utils::coords::ChunkCoord;
+use amethyst::ecs::Entity;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkEventKind {
+    Spawned,     // chunk entity created, no voxels yet
+    VoxelsReady, // `VoxelData` generated
+    Meshed,      // mesh (re)built
+    Unloaded,    // entity deleted, the entity is no longer valid when this is read
+    Modified,    // `VoxelData` replaced in place, whatever was built from it is rebuilt
+}
+
+// published on `EventChannel<ChunkEvent>` by the terrain systems
+#[derive(Clone, Copy, Debug)]
+pub struct ChunkEvent {
+    pub kind: ChunkEventKind,
+    pub coord: ChunkCoord,
+    pub entity: Entity,
+}
+
+impl ChunkEvent {
+    pub fn new(kind: ChunkEventKind, coord: ChunkCoord, entity: Entity) -> Self {
+        Self {
+            kind,
+            coord,
+            entity,
+        }
+    }
+}
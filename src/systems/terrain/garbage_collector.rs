@@ -0,0 +1,72 @@
+use super::{ChunkEvent, ChunkEventKind};
+use crate::{
+    components::terrain::ChunkWater, resources::chunk_map::ChunkMap, utils::coords::ChunkCoord,
+};
+
+use amethyst::{
+    controls::FlyControlTag,
+    core::{SystemDesc, Transform},
+    derive::SystemDesc,
+    ecs::prelude::*,
+    shrev::EventChannel,
+};
+
+// unloads chunks that are too far from the camera
+#[derive(Default, SystemDesc)]
+pub struct ChunkGarbageCollectorSystem;
+
+impl<'a> System<'a> for ChunkGarbageCollectorSystem {
+    type SystemData = (
+        Read<'a, super::TerrainSettings>,
+        Write<'a, ChunkMap>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, FlyControlTag>,
+        ReadStorage<'a, ChunkWater>,
+        Entities<'a>,
+        Write<'a, EventChannel<ChunkEvent>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (settings, mut chunk_map, transform, control_tag, waters, entities, mut events) = data;
+
+        if let Some((position, _)) = (&transform, &control_tag).join().next() {
+            let current = ChunkCoord::from_world(position.translation().into(), &settings);
+
+            let to_remove = chunk_map
+                .iter()
+                .filter(|(coord, _)| coord.distance(current) > settings.unload_radius)
+                .collect::<Vec<_>>();
+
+            for (coord, entity) in to_remove {
+                unload_chunk(
+                    coord,
+                    entity,
+                    &mut chunk_map,
+                    &waters,
+                    &entities,
+                    &mut events,
+                );
+            }
+        }
+    }
+}
+
+// deletes a chunk with its water surface, also used when the terrain config is reloaded
+pub fn unload_chunk(
+    coord: ChunkCoord,
+    entity: Entity,
+    chunk_map: &mut ChunkMap,
+    waters: &ReadStorage<ChunkWater>,
+    entities: &Entities,
+    events: &mut EventChannel<ChunkEvent>,
+) {
+    log::info!("Removed chunk {:?}", coord);
+    if let Some(surface) = waters.get(entity).and_then(|w| w.surface) {
+        entities
+            .delete(surface)
+            .expect("failed to delete water surface");
+    }
+    entities.delete(entity).expect("failed to delete chunk");
+    chunk_map.remove(coord);
+    events.single_write(ChunkEvent::new(ChunkEventKind::Unloaded, coord, entity));
+}
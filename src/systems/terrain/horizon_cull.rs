@@ -0,0 +1,119 @@
+use crate::{
+    components::terrain::{Chunk, Occluded, VoxelData},
+    resources::terrain::TerrainQuery,
+    utils::coords::WorldPos,
+};
+use amethyst::{
+    controls::FlyControlTag,
+    core::{math::Vector3, Transform},
+    ecs::prelude::*,
+};
+
+// marks chunks that are waiting for a mesh but are hidden behind nearer terrain
+// only chunks without a mesh are checked, once built a mesh is kept even if it goes out of sight
+#[derive(Default)]
+pub struct HorizonCullSystem;
+
+impl<'a> System<'a> for HorizonCullSystem {
+    type SystemData = (
+        Read<'a, super::TerrainSettings>,
+        TerrainQuery<'a>,
+        Entities<'a>,
+        ReadStorage<'a, Chunk>,
+        ReadStorage<'a, VoxelData>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, FlyControlTag>,
+        WriteStorage<'a, Occluded>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            settings,
+            terrain,
+            entities,
+            chunks,
+            voxel_data,
+            transforms,
+            control_tags,
+            mut occluded,
+        ) = data;
+
+        if !settings.horizon_culling {
+            occluded.clear();
+            return;
+        }
+
+        let eye = match (&transforms, &control_tags).join().next() {
+            Some((transform, _)) => *transform.translation(),
+            None => return,
+        };
+
+        let pending = (&*entities, &chunks, &voxel_data, !&transforms)
+            .join()
+            .map(|(entity, chunk, voxels, _)| {
+                let top = voxels
+                    .voxels
+                    .iter()
+                    .map(|v| v.heights[0])
+                    .fold(std::f32::NEG_INFINITY, f32::max);
+                (entity, chunk.coord.center(&settings), top)
+            })
+            .collect::<Vec<_>>();
+
+        for (entity, center, top) in pending {
+            if is_occluded(&eye, center, top, &terrain, &settings) {
+                occluded.insert(entity, Occluded).unwrap();
+            } else {
+                occluded.remove(entity);
+            }
+        }
+    }
+}
+
+// a chunk is hidden when every ray from the eye to its corners passes below nearer terrain,
+// the chunk is assumed to reach its highest point at its closest spot to be conservative
+fn is_occluded(
+    eye: &Vector3<f32>,
+    center: WorldPos,
+    top: f32,
+    terrain: &TerrainQuery,
+    settings: &super::TerrainSettings,
+) -> bool {
+    let half = settings.chunk_length() / 2.;
+    let nearest_x = eye.x.max(center.x - half).min(center.x + half);
+    let nearest_z = eye.z.max(center.z - half).min(center.z + half);
+    let near_distance = ((nearest_x - eye.x).powi(2) + (nearest_z - eye.z).powi(2)).sqrt();
+
+    // the chunk the camera is on and its neighbours are always visible
+    if near_distance < settings.chunk_length() {
+        return false;
+    }
+    let chunk_slope = (top - eye.y) / near_distance;
+
+    [(-1., -1.), (1., -1.), (-1., 1.), (1., 1.)]
+        .iter()
+        .all(|(sx, sz)| {
+            let corner = WorldPos::new(center.x + sx * half, center.z + sz * half);
+            horizon_slope(eye, corner, near_distance, terrain, settings) > chunk_slope
+        })
+}
+
+// steepest slope from the eye to the ground along the ray towards `target`, up to `max_distance`
+fn horizon_slope(
+    eye: &Vector3<f32>,
+    target: WorldPos,
+    max_distance: f32,
+    terrain: &TerrainQuery,
+    settings: &super::TerrainSettings,
+) -> f32 {
+    let direction = Vector3::new(target.x - eye.x, 0., target.z - eye.z).normalize();
+    let step = settings.voxel_size;
+    let mut slope = std::f32::NEG_INFINITY;
+    let mut distance = step;
+    while distance < max_distance {
+        let p = eye + direction * distance;
+        slope = slope.max((terrain.height_at(p.x, p.z) - eye.y) / distance);
+        distance += step;
+    }
+    slope
+}
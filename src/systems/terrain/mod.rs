@@ -1,21 +1,66 @@
+use crate::{
+    resources::{
+        chunk_map::ChunkMap,
+        terrain::{HeightGenerator, DEFAULT_SEED},
+    },
+    utils::{erosion::HeightPass, heightmap::HeightmapSettings},
+};
 use amethyst::{
     core::{ecs::prelude::*, SystemBundle},
+    utils::application_root_dir,
     Error,
 };
+use serde::{Deserialize, Serialize};
 
 mod chunk_lod;
 mod chunk_mesh_builder;
 mod chunk_spawner;
+mod events;
+mod garbage_collector;
+mod horizon_cull;
+mod prop_cull;
+mod prop_scatter;
 mod voxel_generator;
+mod water_builder;
 
 pub use chunk_lod::ChunkLodSystem;
 pub use chunk_mesh_builder::ChunkMeshBuilderSystem;
 pub use chunk_spawner::ChunkSpawnerSystem;
+pub use events::{ChunkEvent, ChunkEventKind};
+pub use garbage_collector::ChunkGarbageCollectorSystem;
+pub use horizon_cull::HorizonCullSystem;
+pub use prop_cull::PropCullSystem;
+pub use prop_scatter::{PropScatterSystem, PropStats, ScatterConfig};
 pub use voxel_generator::VoxelGeneratorSystem;
+pub use water_builder::WaterBuilderSystem;
+
+// used by hot reloading to rebuild chunks
+#[cfg(feature = "dev")]
+pub use garbage_collector::unload_chunk;
+#[cfg(feature = "dev")]
+pub use voxel_generator::generate_voxels;
 
+// loaded from `config/terrain.ron`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct TerrainSettings {
-    pub chunk_size: i32, // voxels per side
-    pub voxel_size: f32, // length of voxel side
+    pub chunk_size: i32,               // voxels per side
+    pub voxel_size: f32,               // length of voxel side
+    pub sea_level: f32,                // height of the water surface
+    pub load_radius: i32,              // chunks around the camera to create
+    pub unload_radius: i32,            // chunks further than this are removed
+    pub post_process: Vec<HeightPass>, // applied in order to generated heights
+    pub spawns_per_frame: usize,       // chunks created per frame, most visible first
+    pub horizon_culling: bool,         // don't mesh chunks hidden behind nearer terrain
+    // heights are read from this instead of generated from noise when set
+    pub heightmap: Option<HeightmapSettings>,
+}
+
+impl TerrainSettings {
+    // length of a chunk side in world units
+    pub fn chunk_length(&self) -> f32 {
+        self.chunk_size as f32 * self.voxel_size
+    }
 }
 
 impl Default for TerrainSettings {
@@ -23,12 +68,34 @@ impl Default for TerrainSettings {
         Self {
             chunk_size: 50,
             voxel_size: 30.,
+            sea_level: -10.,
+            load_radius: 1,
+            unload_radius: 2,
+            post_process: Vec::new(),
+            spawns_per_frame: 2,
+            horizon_culling: false,
+            heightmap: None,
         }
     }
 }
 
 #[derive(Default, Debug)]
-pub struct TerrainBundle;
+pub struct TerrainBundle {
+    settings: TerrainSettings,
+    scatter: ScatterConfig,
+}
+
+impl TerrainBundle {
+    pub fn with_settings(mut self, settings: TerrainSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_scatter_config(mut self, scatter: ScatterConfig) -> Self {
+        self.scatter = scatter;
+        self
+    }
+}
 
 impl<'a, 'b> SystemBundle<'a, 'b> for TerrainBundle {
     fn build(
@@ -36,18 +103,48 @@ impl<'a, 'b> SystemBundle<'a, 'b> for TerrainBundle {
         world: &mut World,
         builder: &mut DispatcherBuilder<'a, 'b>,
     ) -> Result<(), Error> {
-        world.insert(TerrainSettings::default());
+        let generator =
+            HeightGenerator::from_settings(DEFAULT_SEED, &self.settings, &application_root_dir()?)?;
+        world.insert(self.settings);
+        world.insert(generator);
+        world.insert(self.scatter);
+        world.insert(ChunkMap::default());
         builder.add(ChunkSpawnerSystem::default(), "terrain_chunk_spawner", &[]);
+        builder.add(
+            ChunkGarbageCollectorSystem::default(),
+            "terrain_garbage_collector",
+            &["terrain_chunk_spawner"],
+        );
         builder.add(ChunkLodSystem, "terrain_lod", &["terrain_chunk_spawner"]);
         builder.add(
             VoxelGeneratorSystem::default(),
             "terrain_voxel_generator",
             &["terrain_chunk_spawner"],
         );
+        builder.add(
+            HorizonCullSystem::default(),
+            "terrain_horizon_cull",
+            &["terrain_voxel_generator"],
+        );
         builder.add(
             ChunkMeshBuilderSystem::default(),
             "terrain_mesh_builder",
-            &["terrain_voxel_generator", "terrain_lod"],
+            &["terrain_voxel_generator", "terrain_lod", "terrain_horizon_cull"],
+        );
+        builder.add(
+            PropScatterSystem::default(),
+            "terrain_prop_scatter",
+            &["terrain_voxel_generator", "terrain_garbage_collector"],
+        );
+        builder.add(
+            PropCullSystem::default(),
+            "terrain_prop_cull",
+            &["terrain_prop_scatter"],
+        );
+        builder.add(
+            WaterBuilderSystem::default(),
+            "terrain_water_builder",
+            &["terrain_mesh_builder"],
         );
 
         Ok(())
@@ -0,0 +1,84 @@
+use super::{PropStats, ScatterConfig};
+use crate::components::prop::PropBatch;
+
+use amethyst::{
+    controls::FlyControlTag,
+    core::{Hidden, Transform},
+    ecs::prelude::*,
+    renderer::{palette::Srgba, resources::Tint, transparent::Transparent},
+};
+
+// hides prop batches far from the camera, fading them out first
+#[derive(Default)]
+pub struct PropCullSystem;
+
+impl<'a> System<'a> for PropCullSystem {
+    type SystemData = (
+        Read<'a, ScatterConfig>,
+        Write<'a, PropStats>,
+        Entities<'a>,
+        ReadStorage<'a, FlyControlTag>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, PropBatch>,
+        WriteStorage<'a, Hidden>,
+        WriteStorage<'a, Tint>,
+        WriteStorage<'a, Transparent>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            config,
+            mut stats,
+            entities,
+            control_tag,
+            transforms,
+            batches,
+            mut hidden,
+            mut tints,
+            mut transparent,
+        ) = data;
+
+        let camera = match (&transforms, &control_tag).join().next() {
+            Some((transform, _)) => *transform.translation(),
+            None => return,
+        };
+        let fade_start = config.cull_distance - config.fade_distance;
+
+        stats.batches = 0;
+        stats.visible_batches = 0;
+        stats.batched_props = 0;
+
+        for (entity, batch) in (&*entities, &batches).join() {
+            stats.batches += 1;
+            stats.batched_props += batch.props;
+
+            let offset = batch.chunk_center - camera;
+            let distance = (offset.x * offset.x + offset.z * offset.z).sqrt();
+
+            if distance >= config.cull_distance {
+                if !hidden.contains(entity) {
+                    hidden.insert(entity, Hidden).expect("hidden insert failed");
+                }
+                continue;
+            }
+
+            stats.visible_batches += 1;
+            hidden.remove(entity);
+
+            if distance > fade_start && config.fade_distance > 0. {
+                let alpha = (config.cull_distance - distance) / config.fade_distance;
+                tints
+                    .insert(entity, Tint(Srgba::new(1., 1., 1., alpha)))
+                    .expect("tint insert failed");
+                if !transparent.contains(entity) {
+                    transparent
+                        .insert(entity, Transparent)
+                        .expect("transparent insert failed");
+                }
+            } else if tints.contains(entity) {
+                tints.remove(entity);
+                transparent.remove(entity);
+            }
+        }
+    }
+}
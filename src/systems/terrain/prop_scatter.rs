@@ -0,0 +1,409 @@
+use super::{ChunkEvent, ChunkEventKind};
+use crate::{
+    components::prop::{PropBatch, PropPrefabData, PropShape},
+    resources::{
+        prefabs::PrefabRegistry,
+        terrain::{Biome, TerrainQuery},
+    },
+    utils::{
+        coords::ChunkCoord,
+        mesh::create_batch_mesh,
+        scatter::{poisson_disc, Placement, SeededRng},
+    },
+};
+
+use amethyst::{
+    assets::{AssetLoaderSystemData, AssetStorage, Handle, Prefab},
+    core::{
+        math::{Point3, Vector3},
+        Transform,
+    },
+    ecs::prelude::*,
+    renderer::{
+        mtl::MaterialDefaults,
+        palette::LinSrgba,
+        rendy::{
+            mesh::{Normal, Position, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        types::Mesh,
+        visibility::BoundingSphere,
+        Material, Texture,
+    },
+    shrev::{EventChannel, ReaderId},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// where a prop may be placed, loaded from `config/scatter.ron`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScatterRule {
+    pub prefab: String,
+    pub spacing: f32, // minimum distance between two props of this rule
+    pub min_height: f32,
+    pub max_height: f32,
+    pub max_steepness: f32, // 0 is flat, 1 is a vertical wall
+    pub biomes: Vec<Biome>, // empty means any
+    pub density: f32,       // chance to keep each candidate
+    pub scale: (f32, f32),  // uniform scale range
+}
+
+impl Default for ScatterRule {
+    fn default() -> Self {
+        Self {
+            prefab: String::new(),
+            spacing: 50.,
+            min_height: std::f32::MIN,
+            max_height: std::f32::MAX,
+            max_steepness: 1.,
+            biomes: Vec::new(),
+            density: 1.,
+            scale: (1., 1.),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ScatterConfig {
+    pub rules: Vec<ScatterRule>,
+    pub cull_distance: f32, // batches further than this from the camera are hidden
+    pub fade_distance: f32, // batches fade out over this distance before being culled
+}
+
+impl Default for ScatterConfig {
+    fn default() -> Self {
+        Self {
+            rules: Vec::new(),
+            cull_distance: 2000.,
+            fade_distance: 300.,
+        }
+    }
+}
+
+// counters shown by the debug overlay
+#[derive(Clone, Debug, Default)]
+pub struct PropStats {
+    pub batches: usize,
+    pub visible_batches: usize,
+    pub batched_props: usize,
+    pub instances: usize, // props spawned as individual prefabs
+}
+
+impl PropStats {
+    // every visible batch and every prefab instance is (at least) one draw call
+    pub fn draw_calls(&self) -> usize {
+        self.visible_batches + self.instances
+    }
+}
+
+type ShapeVertices = (Vec<Position>, Vec<Normal>, Vec<TexCoord>);
+
+// places props on chunks once their voxels are generated (again when they change),
+// and removes them with the chunk
+// props with a batch shape are merged into one mesh per chunk and prop kind
+#[derive(Default)]
+pub struct PropScatterSystem {
+    reader_id: Option<ReaderId<ChunkEvent>>,
+    props: HashMap<ChunkCoord, Vec<Entity>>,
+    instances: usize,
+    shapes: HashMap<String, BatchShape>, // by rule prefab name
+}
+
+// the batch mesh data of a prop, built again once its prefab is reloaded
+struct BatchShape {
+    prefab: Handle<Prefab<PropPrefabData>>, // the prefab it was built from
+    vertices: ShapeVertices,
+    material: Handle<Material>,
+}
+
+impl<'a> System<'a> for PropScatterSystem {
+    type SystemData = (
+        Read<'a, ScatterConfig>,
+        Read<'a, super::TerrainSettings>,
+        TerrainQuery<'a>,
+        Read<'a, PrefabRegistry<Prefab<PropPrefabData>>>,
+        Read<'a, AssetStorage<Prefab<PropPrefabData>>>,
+        Write<'a, EventChannel<ChunkEvent>>,
+        Write<'a, PropStats>,
+        Entities<'a>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Handle<Prefab<PropPrefabData>>>,
+        (
+            ReadExpect<'a, MaterialDefaults>,
+            AssetLoaderSystemData<'a, Mesh>,
+            AssetLoaderSystemData<'a, Texture>,
+            AssetLoaderSystemData<'a, Material>,
+            WriteStorage<'a, Handle<Mesh>>,
+            WriteStorage<'a, Handle<Material>>,
+            WriteStorage<'a, BoundingSphere>,
+            WriteStorage<'a, PropBatch>,
+        ),
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(
+            world
+                .fetch_mut::<EventChannel<ChunkEvent>>()
+                .register_reader(),
+        );
+    }
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            config,
+            settings,
+            terrain,
+            prefabs,
+            prefab_storage,
+            events,
+            mut stats,
+            entities,
+            mut transforms,
+            mut prefab_handles,
+            (
+                material_defaults,
+                mesh_loader,
+                texture_loader,
+                material_loader,
+                mut meshes,
+                mut materials,
+                mut bounds,
+                mut batches,
+            ),
+        ) = data;
+
+        let reader_id = self.reader_id.as_mut().expect("setup was not called");
+        let chunk_events = events.read(reader_id).cloned().collect::<Vec<_>>();
+
+        for event in chunk_events {
+            match event.kind {
+                ChunkEventKind::VoxelsReady | ChunkEventKind::Modified => {
+                    let mut spawned = Vec::new();
+                    let chunk_center = event.coord.center(&settings).to_vector(0.);
+
+                    for rule in config.rules.iter() {
+                        let prefab = match prefabs.get_prefab(&rule.prefab) {
+                            Some(prefab) => prefab,
+                            None => {
+                                log::warn!("Scatter prefab {} not found", rule.prefab);
+                                continue;
+                            }
+                        };
+                        let placements = place_props(rule, event.coord, &terrain, &settings);
+                        if placements.is_empty() {
+                            continue;
+                        }
+
+                        let batch_shape = prefab_storage
+                            .get(prefab)
+                            .and_then(|p| p.entities().next())
+                            .and_then(|e| e.data())
+                            .and_then(|d| d.batch.as_ref());
+
+                        match batch_shape {
+                            Some(shape) => {
+                                // batch vertices are relative to the chunk center
+                                let local = placements
+                                    .into_iter()
+                                    .map(|p| Placement {
+                                        translation: p.translation - chunk_center,
+                                        ..p
+                                    })
+                                    .collect::<Vec<_>>();
+                                let stale = self
+                                    .shapes
+                                    .get(&rule.prefab)
+                                    .map_or(true, |cached| cached.prefab != *prefab);
+                                if stale {
+                                    let cached = BatchShape {
+                                        prefab: prefab.clone(),
+                                        vertices: shape
+                                            .shape
+                                            .generate_vertices::<ShapeVertices>(shape.scale),
+                                        material: create_material(
+                                            shape,
+                                            &material_defaults,
+                                            &texture_loader,
+                                            &material_loader,
+                                        ),
+                                    };
+                                    self.shapes.insert(rule.prefab.clone(), cached);
+                                }
+                                let BatchShape {
+                                    vertices, material, ..
+                                } = &self.shapes[&rule.prefab];
+
+                                let mesh = mesh_loader
+                                    .load_from_data(create_batch_mesh(vertices, &local), ());
+                                let bound = batch_bounds(vertices, &local);
+
+                                spawned.push(
+                                    entities
+                                        .build_entity()
+                                        .with(Transform::from(chunk_center), &mut transforms)
+                                        .with(mesh, &mut meshes)
+                                        .with(material.clone(), &mut materials)
+                                        .with(bound, &mut bounds)
+                                        .with(
+                                            PropBatch {
+                                                chunk_center,
+                                                props: local.len(),
+                                            },
+                                            &mut batches,
+                                        )
+                                        .build(),
+                                );
+                            }
+                            None => {
+                                self.instances += placements.len();
+                                for placement in placements {
+                                    let mut transform = Transform::default();
+                                    transform.set_translation(placement.translation);
+                                    transform.set_rotation_y_axis(placement.angle);
+                                    transform.set_scale(Vector3::new(
+                                        placement.scale,
+                                        placement.scale,
+                                        placement.scale,
+                                    ));
+
+                                    spawned.push(
+                                        entities
+                                            .build_entity()
+                                            .with(transform, &mut transforms)
+                                            .with(prefab.clone(), &mut prefab_handles)
+                                            .build(),
+                                    );
+                                }
+                            }
+                        }
+                    }
+
+                    log::info!("Scattered {} props on {:?}", spawned.len(), event.coord);
+                    if let Some(previous) = self.props.insert(event.coord, spawned) {
+                        self.delete_props(&entities, &prefab_handles, previous);
+                    }
+                }
+                ChunkEventKind::Unloaded => {
+                    if let Some(props) = self.props.remove(&event.coord) {
+                        self.delete_props(&entities, &prefab_handles, props);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        stats.instances = self.instances;
+    }
+}
+
+impl PropScatterSystem {
+    fn delete_props(
+        &mut self,
+        entities: &Entities,
+        prefab_handles: &WriteStorage<Handle<Prefab<PropPrefabData>>>,
+        props: Vec<Entity>,
+    ) {
+        for prop in props {
+            if prefab_handles.contains(prop) {
+                self.instances -= 1;
+            }
+            if let Err(e) = entities.delete(prop) {
+                log::warn!("Failed to delete prop {:?}: {:?}", prop, e);
+            }
+        }
+    }
+}
+
+// deterministic placements (in world space) of a rule on a chunk
+fn place_props(
+    rule: &ScatterRule,
+    coord: ChunkCoord,
+    terrain: &TerrainQuery,
+    settings: &super::TerrainSettings,
+) -> Vec<Placement> {
+    let chunk_length = settings.chunk_length();
+    let center = coord.center(settings);
+    let mut rng = SeededRng::for_chunk(terrain.seed(), coord, rule_salt(rule));
+
+    poisson_disc(&mut rng, chunk_length, rule.spacing)
+        .into_iter()
+        .filter_map(|(px, pz)| {
+            // always draw the same numbers so placements don't shift when the filters change
+            let keep = rng.next_f32();
+            let scale = rng.range(rule.scale.0, rule.scale.1);
+            let angle = rng.range(0., std::f32::consts::PI * 2.);
+
+            let x = center.x - chunk_length / 2. + px;
+            let z = center.z - chunk_length / 2. + pz;
+            let height = terrain.height_at(x, z);
+            let steepness = 1. - terrain.normal_at(x, z).y;
+            let biome = Biome::classify(height, steepness, settings);
+
+            if keep >= rule.density
+                || height < rule.min_height
+                || height > rule.max_height
+                || steepness > rule.max_steepness
+                || (!rule.biomes.is_empty() && !rule.biomes.contains(&biome))
+            {
+                return None;
+            }
+
+            Some(Placement {
+                translation: Vector3::new(x, height, z),
+                angle,
+                scale,
+            })
+        })
+        .collect()
+}
+
+// salted by the prefab name rather than the rule's position,
+// so adding or reordering rules leaves the other rules' placements alone
+fn rule_salt(rule: &ScatterRule) -> u32 {
+    // fnv-1a, stable across builds unlike the std hasher
+    rule.prefab.bytes().fold(0x811C_9DC5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+    })
+}
+
+fn create_material(
+    shape: &PropShape,
+    defaults: &MaterialDefaults,
+    texture_loader: &AssetLoaderSystemData<Texture>,
+    material_loader: &AssetLoaderSystemData<Material>,
+) -> Handle<Material> {
+    let (r, g, b, a) = shape.color;
+    let albedo =
+        texture_loader.load_from_data(load_from_linear_rgba(LinSrgba::new(r, g, b, a)).into(), ());
+    material_loader.load_from_data(
+        Material {
+            albedo,
+            ..defaults.0.clone()
+        },
+        (),
+    )
+}
+
+// sphere around all placements, grown by the largest extent of the shape
+fn batch_bounds(vertices: &ShapeVertices, placements: &[Placement]) -> BoundingSphere {
+    let extent = vertices
+        .0
+        .iter()
+        .map(|p| Vector3::from(p.0).norm())
+        .fold(0., f32::max);
+
+    let count = placements.len().max(1) as f32;
+    let center = placements
+        .iter()
+        .fold(Vector3::zeros(), |sum, p| sum + p.translation)
+        / count;
+    let radius = placements
+        .iter()
+        .map(|p| (p.translation - center).norm() + extent * p.scale)
+        .fold(0., f32::max);
+
+    BoundingSphere::new(Point3::from(center), radius)
+}
@@ -1,78 +1,79 @@
-use crate::components::terrain::{Chunk, Voxel, VoxelData};
-use amethyst::core::math::*;
-use amethyst::ecs::prelude::*;
-use noise::*;
+use super::{ChunkEvent, ChunkEventKind};
+use crate::{
+    components::terrain::{Chunk, Voxel, VoxelData},
+    resources::terrain::HeightGenerator,
+    utils::coords::{ChunkCoord, VoxelCoord},
+};
+use amethyst::{ecs::prelude::*, shrev::EventChannel};
 
-pub struct VoxelGeneratorSystem {
-    noise_generator: Perlin,
-}
+// offsets of the heights stored in each voxel, the voxel itself comes first
+const NEIGHBOURS: [(i32, i32); 9] = [
+    (0, 0),
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (-1, -1),
+    (1, -1),
+    (-1, 1),
+];
 
-impl Default for VoxelGeneratorSystem {
-    fn default() -> Self {
-        Self {
-            noise_generator: Perlin::new().set_seed(20),
-        }
-    }
-}
+#[derive(Default)]
+pub struct VoxelGeneratorSystem;
 
 impl<'a> System<'a> for VoxelGeneratorSystem {
     type SystemData = (
         Read<'a, super::TerrainSettings>,
+        ReadExpect<'a, HeightGenerator>,
         ReadStorage<'a, Chunk>,
         WriteStorage<'a, VoxelData>,
         Entities<'a>,
+        Write<'a, EventChannel<ChunkEvent>>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (settings, chunks, mut voxel_data, entities) = data;
+        let (settings, generator, chunks, mut voxel_data, entities, mut events) = data;
 
         let entities_to_modify = (&entities, &chunks, !&voxel_data)
             .join()
             .map(|(entity, chunk, _)| (entity, chunk))
             .collect::<Vec<_>>();
 
-        // TODO: optimize (duplicate calcs)
         for (entity, chunk) in entities_to_modify {
-            let voxels = (0..(settings.chunk_size * settings.chunk_size))
-                .map(|i| {
-                    let x = i % settings.chunk_size;
-                    let y = i / settings.chunk_size;
-                    let (abs_x, abs_y) = get_abs((x, y), chunk, &settings);
-                    let heights = [
-                        (x, y),
-                        (x + 1, y),
-                        (x - 1, y),
-                        (x, y + 1),
-                        (x, y - 1),
-                        (x + 1, y + 1),
-                        (x - 1, y - 1),
-                        (x + 1, y - 1),
-                        (x - 1, y + 1),
-                    ]
-                    .iter()
-                    .map(|&xy| get_abs(xy, chunk, &settings))
-                    .map(|(abs_x, abs_y)| self.get_height(abs_x, abs_y))
-                    .collect();
-
-                    Voxel::new(x, y, abs_x, abs_y, heights)
-                })
-                .collect::<Vec<_>>();
-
-            voxel_data.insert(entity, VoxelData::new(voxels)).unwrap();
+            voxel_data
+                .insert(entity, generate_voxels(&settings, &generator, chunk.coord))
+                .unwrap();
+            events.single_write(ChunkEvent::new(
+                ChunkEventKind::VoxelsReady,
+                chunk.coord,
+                entity,
+            ));
         }
     }
 }
 
-fn get_abs((x, y): (i32, i32), chunk: &Chunk, settings: &super::TerrainSettings) -> (f32, f32) {
-    let offset = (settings.chunk_size as f32 * settings.voxel_size) / 2.;
-    (
-        chunk.x - offset + (x as f32 * settings.voxel_size),
-        chunk.y - offset + (y as f32 * settings.voxel_size),
-    )
-}
+// the voxels of a chunk, also used to regenerate loaded chunks when the terrain config changes
+pub fn generate_voxels(
+    settings: &super::TerrainSettings,
+    generator: &HeightGenerator,
+    coord: ChunkCoord,
+) -> VoxelData {
+    let (grid, margin) = generator.chunk_heights(settings, coord);
 
-impl VoxelGeneratorSystem {
-    fn get_height(&self, x: f32, y: f32) -> f32 {
-        (self.noise_generator.get([x as f64 / 100., y as f64 / 100.]) * 30.) as f32
-    }
+    let voxels = (0..(settings.chunk_size * settings.chunk_size))
+        .map(|i| {
+            let x = i % settings.chunk_size;
+            let y = i / settings.chunk_size;
+            let abs = VoxelCoord::from_local(coord, x, y, settings).corner(settings);
+            let heights = NEIGHBOURS
+                .iter()
+                .map(|(dx, dy)| grid.get((x + dx + margin) as usize, (y + dy + margin) as usize))
+                .collect();
+
+            Voxel::new(x, y, abs.x, abs.z, heights)
+        })
+        .collect::<Vec<_>>();
+
+    VoxelData::new(voxels)
 }
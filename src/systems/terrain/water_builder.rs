@@ -0,0 +1,120 @@
+use super::{ChunkEvent, ChunkEventKind};
+use crate::components::terrain::{Chunk, ChunkWater, VoxelData};
+
+use amethyst::{
+    assets::{AssetLoaderSystemData, Handle},
+    core::{
+        math::*,
+        transform::{Parent, Transform},
+    },
+    ecs::prelude::*,
+    renderer::{transparent::Transparent, types::Mesh, Material},
+    shrev::{EventChannel, ReaderId},
+};
+
+// adds a water surface to chunks that dip below sea level, rebuilt when their voxels change
+#[derive(Default)]
+pub struct WaterBuilderSystem {
+    reader_id: Option<ReaderId<ChunkEvent>>,
+}
+
+impl<'a> System<'a> for WaterBuilderSystem {
+    type SystemData = (
+        Read<'a, super::TerrainSettings>,
+        Entities<'a>,
+        ReadStorage<'a, Chunk>,
+        ReadStorage<'a, VoxelData>,
+        WriteStorage<'a, ChunkWater>,
+        AssetLoaderSystemData<'a, Mesh>,
+        WriteStorage<'a, Handle<Mesh>>,
+        WriteStorage<'a, Handle<Material>>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Parent>,
+        WriteStorage<'a, Transparent>,
+        Read<'a, crate::states::game::Hax>,
+        Read<'a, EventChannel<ChunkEvent>>,
+    );
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader_id = Some(
+            world
+                .fetch_mut::<EventChannel<ChunkEvent>>()
+                .register_reader(),
+        );
+    }
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            settings,
+            entities,
+            chunks,
+            voxel_data,
+            mut waters,
+            mesh_loader,
+            mut meshes,
+            mut materials,
+            mut transforms,
+            mut parents,
+            mut transparents,
+            hax,
+            events,
+        ) = data;
+
+        let reader_id = self.reader_id.as_mut().expect("setup was not called");
+        for event in events.read(reader_id) {
+            if event.kind != ChunkEventKind::Modified {
+                continue;
+            }
+            if let Some(water) = waters.remove(event.entity) {
+                if let Some(surface) = water.surface {
+                    entities
+                        .delete(surface)
+                        .expect("failed to delete water surface");
+                }
+            }
+        }
+
+        if let Some(material) = hax.water_material.as_ref() {
+            // only chunks that already have a mesh (and therefore a transform) get water
+            let to_create = (&*entities, &chunks, &voxel_data, &transforms, !&waters)
+                .join()
+                .map(|(entity, _, voxel, _, _)| {
+                    let lowest = voxel
+                        .voxels
+                        .iter()
+                        .flat_map(|v| v.heights.iter().cloned())
+                        .fold(std::f32::INFINITY, f32::min);
+                    (entity, lowest)
+                })
+                .collect::<Vec<_>>();
+
+            let chunk_size = settings.chunk_size as f32 * settings.voxel_size;
+
+            for (entity, lowest) in to_create.into_iter() {
+                let surface = if lowest < settings.sea_level {
+                    let mesh = mesh_loader
+                        .load_from_data(crate::utils::mesh::create_water_mesh(chunk_size / 2.), ());
+                    let water = entities
+                        .build_entity()
+                        .with(
+                            Transform::from(Vector3::new(0., settings.sea_level, 0.)),
+                            &mut transforms,
+                        )
+                        .with(Parent::new(entity), &mut parents)
+                        .with(mesh, &mut meshes)
+                        .with(material.clone(), &mut materials)
+                        .with(Transparent, &mut transparents)
+                        .build();
+                    Some(water)
+                } else {
+                    None
+                };
+
+                waters
+                    .insert(entity, ChunkWater::new(surface))
+                    .expect("chunk water insert failed");
+            }
+        }
+    }
+}
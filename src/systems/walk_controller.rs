@@ -0,0 +1,141 @@
+use crate::{
+    bindings::{ActionBinding, AxisBinding, GameBindings},
+    components::walker::WalkState,
+    resources::terrain::TerrainQuery,
+};
+use amethyst::{
+    controls::FlyControlTag,
+    core::{math::Vector3, Time, Transform},
+    ecs::prelude::*,
+    input::InputHandler,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ControlMode {
+    Fly,
+    Walk,
+}
+
+impl Default for ControlMode {
+    fn default() -> Self {
+        ControlMode::Fly
+    }
+}
+
+pub struct WalkSettings {
+    pub speed: f32,
+    pub gravity: f32,
+    pub jump_speed: f32,
+    pub eye_height: f32, // distance between the ground and the controlled entity
+    pub max_steepness: f32, // steepest ground that can be walked up (1 - normal.y)
+}
+
+impl Default for WalkSettings {
+    fn default() -> Self {
+        Self {
+            speed: 60.,
+            gravity: 60.,
+            jump_speed: 30.,
+            eye_height: 6.,
+            max_steepness: 0.4,
+        }
+    }
+}
+
+// keeps fly controlled entities on the ground while in walk mode
+// runs after the fly movement and overwrites its translation, rotation is left to the
+// fly control bundle so mouse look keeps working
+#[derive(Default)]
+pub struct WalkControllerSystem {
+    toggle_was_down: bool,
+}
+
+impl<'a> System<'a> for WalkControllerSystem {
+    type SystemData = (
+        Read<'a, Time>,
+        Read<'a, InputHandler<GameBindings>>,
+        Read<'a, WalkSettings>,
+        Write<'a, ControlMode>,
+        TerrainQuery<'a>,
+        Entities<'a>,
+        ReadStorage<'a, FlyControlTag>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, WalkState>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            time,
+            input,
+            settings,
+            mut mode,
+            terrain,
+            entities,
+            control_tags,
+            mut transforms,
+            mut walk_states,
+        ) = data;
+
+        let toggle_down = input
+            .action_is_down(&ActionBinding::ToggleWalk)
+            .unwrap_or(false);
+        if toggle_down && !self.toggle_was_down {
+            *mode = match *mode {
+                ControlMode::Fly => ControlMode::Walk,
+                ControlMode::Walk => ControlMode::Fly,
+            };
+            log::info!("Switched to {:?} mode", *mode);
+        }
+        self.toggle_was_down = toggle_down;
+
+        if *mode == ControlMode::Fly {
+            walk_states.clear();
+            return;
+        }
+
+        let dt = time.delta_seconds();
+        let right = input.axis_value(&AxisBinding::XAxis).unwrap_or(0.);
+        let back = input.axis_value(&AxisBinding::ZAxis).unwrap_or(0.);
+        let jump = input.axis_value(&AxisBinding::YAxis).unwrap_or(0.) > 0.;
+
+        for (entity, transform, _) in (&*entities, &mut transforms, &control_tags).join() {
+            let state = walk_states
+                .entry(entity)
+                .expect("entity was just joined")
+                .or_insert_with(|| WalkState::new(*transform.translation()));
+
+            // move along the view direction, flattened onto the ground plane
+            let local = Vector3::new(right, 0., back);
+            let mut direction = transform.rotation() * local;
+            direction.y = 0.;
+            if direction.norm_squared() > std::f32::EPSILON {
+                let step = direction.normalize() * settings.speed * dt;
+                let target = state.position + step;
+                let uphill = terrain.height_at(target.x, target.z)
+                    > terrain.height_at(state.position.x, state.position.z);
+                let too_steep =
+                    1. - terrain.normal_at(target.x, target.z).y > settings.max_steepness;
+                if !(state.grounded && uphill && too_steep) {
+                    state.position.x = target.x;
+                    state.position.z = target.z;
+                }
+            }
+
+            if jump && state.grounded {
+                state.vertical_speed = settings.jump_speed;
+            }
+            state.vertical_speed -= settings.gravity * dt;
+            state.position.y += state.vertical_speed * dt;
+
+            let ground =
+                terrain.height_at(state.position.x, state.position.z) + settings.eye_height;
+            state.grounded = state.position.y <= ground;
+            if state.grounded {
+                state.position.y = ground;
+                state.vertical_speed = 0.;
+            }
+
+            transform.set_translation(state.position);
+        }
+    }
+}
@@ -1,27 +1,113 @@
 use crate::utils::errors::AssetEnumerationError;
 use amethyst::utils::application_root_dir;
-use std::{fs::read_dir, path::PathBuf};
+use std::{fs::read_dir, path::Path};
 
 const ASSET_PATH: &'static str = "assets";
 
-// returns a tuple of (fileName, relativePath)
+// returns a tuple of (fileName, relativePath) for every file below `sub_path`
 // path is relative from assets folder
+// only files with one of `extensions` are returned (all files if empty), hidden files and
+// directories are skipped, symlinked directories aren't followed, entries are sorted by path
 pub fn enumerate_assets(
   sub_path: &str,
-) -> Result<impl Iterator<Item = (String, String)>, AssetEnumerationError> {
-  let path = application_root_dir()?.join(ASSET_PATH).join(sub_path);
-  let base_path = PathBuf::from(sub_path);
-
-  Ok(read_dir(path)?.filter_map(move |entry| {
-    if let Ok(entry) = entry {
-      let path = entry.path();
-
-      return Some((
-        path.file_name()?.to_str()?.to_owned(),
-        base_path.join(path.file_name()?).to_str()?.to_owned(),
-      ));
+  extensions: &[&str],
+) -> Result<
+  impl Iterator<Item = Result<(String, String), AssetEnumerationError>>,
+  AssetEnumerationError,
+> {
+  let root = application_root_dir()
+    .map_err(|error| AssetEnumerationError::Io {
+      path: ASSET_PATH.into(),
+      error,
+    })?
+    .join(ASSET_PATH);
+  let mut entries = Vec::new();
+  walk(&root, Path::new(sub_path), extensions, &mut entries)?;
+
+  Ok(entries.into_iter())
+}
+
+// the top level directory failing is an error, anything below is reported per entry
+fn walk(
+  root: &Path,
+  relative: &Path,
+  extensions: &[&str],
+  entries: &mut Vec<Result<(String, String), AssetEnumerationError>>,
+) -> Result<(), AssetEnumerationError> {
+  let directory = root.join(relative);
+  let read = read_dir(&directory).map_err(|error| AssetEnumerationError::Io {
+    path: directory.clone(),
+    error,
+  })?;
+
+  let mut children = Vec::new();
+  for entry in read {
+    match entry {
+      // the entry's own type, so a symlinked directory is never walked into (and can't loop)
+      Ok(entry) => match entry.file_type() {
+        Ok(file_type) => children.push((entry.path(), file_type.is_dir())),
+        Err(error) => entries.push(Err(AssetEnumerationError::Io {
+          path: entry.path(),
+          error,
+        })),
+      },
+      Err(error) => entries.push(Err(AssetEnumerationError::Io {
+        path: directory.clone(),
+        error,
+      })),
+    }
+  }
+  children.sort();
+
+  for (path, is_dir) in children {
+    let file_name = match path.file_name().and_then(|name| name.to_str()) {
+      Some(name) => name.to_owned(),
+      None => {
+        entries.push(Err(AssetEnumerationError::InvalidPath(path)));
+        continue;
+      }
+    };
+    if file_name.starts_with('.') {
+      continue;
+    }
+
+    let relative_path = relative.join(&file_name);
+    if is_dir {
+      if let Err(error) = walk(root, &relative_path, extensions, entries) {
+        entries.push(Err(error));
+      }
+      continue;
     }
 
-    None
-  }))
+    let matches = extensions.is_empty()
+      || path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| {
+          extensions.iter().any(|e| e.eq_ignore_ascii_case(ext))
+        });
+    if !matches {
+      continue;
+    }
+
+    match relative_path.to_str() {
+      Some(relative_path) => entries.push(Ok((file_name, relative_path.to_owned()))),
+      None => entries.push(Err(AssetEnumerationError::InvalidPath(path))),
+    }
+  }
+
+  Ok(())
+}
+
+// helper for callers which skip the entries that failed, logging them
+pub fn log_failed_entries(
+  entry: Result<(String, String), AssetEnumerationError>,
+) -> Option<(String, String)> {
+  match entry {
+    Ok(entry) => Some(entry),
+    Err(error) => {
+      log::error!("Skipping asset: {}", error);
+      None
+    }
+  }
 }
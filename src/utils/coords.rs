@@ -0,0 +1,229 @@
+use crate::systems::terrain::TerrainSettings;
+use amethyst::core::math::Vector3;
+
+// position on the terrain plane (world x and z)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WorldPos {
+    pub x: f32,
+    pub z: f32,
+}
+
+// index of a chunk, chunk (0, 0) is centered on the world origin
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChunkCoord(pub i32, pub i32);
+
+// global index of a voxel, i.e. `chunk * chunk_size + local voxel`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VoxelCoord(pub i32, pub i32);
+
+impl WorldPos {
+    pub fn new(x: f32, z: f32) -> Self {
+        Self { x, z }
+    }
+
+    pub fn to_vector(self, height: f32) -> Vector3<f32> {
+        Vector3::new(self.x, height, self.z)
+    }
+}
+
+impl From<&Vector3<f32>> for WorldPos {
+    fn from(v: &Vector3<f32>) -> Self {
+        Self::new(v.x, v.z)
+    }
+}
+
+impl ChunkCoord {
+    // the chunk containing `pos`
+    pub fn from_world(pos: WorldPos, settings: &TerrainSettings) -> Self {
+        VoxelCoord::from_world(pos, settings).chunk(settings)
+    }
+
+    pub fn center(self, settings: &TerrainSettings) -> WorldPos {
+        let length = settings.chunk_length();
+        WorldPos::new(self.0 as f32 * length, self.1 as f32 * length)
+    }
+
+    // first voxel of the chunk (lowest x and z)
+    pub fn origin_voxel(self, settings: &TerrainSettings) -> VoxelCoord {
+        VoxelCoord(self.0 * settings.chunk_size, self.1 * settings.chunk_size)
+    }
+
+    pub fn offset(self, dx: i32, dy: i32) -> Self {
+        ChunkCoord(self.0 + dx, self.1 + dy)
+    }
+
+    // chebyshev distance, i.e. the ring this chunk is in around `other`
+    pub fn distance(self, other: ChunkCoord) -> i32 {
+        (self.0 - other.0).abs().max((self.1 - other.1).abs())
+    }
+}
+
+impl VoxelCoord {
+    // the voxel containing `pos`
+    pub fn from_world(pos: WorldPos, settings: &TerrainSettings) -> Self {
+        let half_chunk = settings.chunk_size as f32 / 2.;
+        VoxelCoord(
+            (pos.x / settings.voxel_size + half_chunk).floor() as i32,
+            (pos.z / settings.voxel_size + half_chunk).floor() as i32,
+        )
+    }
+
+    pub fn from_local(chunk: ChunkCoord, x: i32, y: i32, settings: &TerrainSettings) -> Self {
+        chunk.origin_voxel(settings).offset(x, y)
+    }
+
+    pub fn chunk(self, settings: &TerrainSettings) -> ChunkCoord {
+        ChunkCoord(
+            self.0.div_euclid(settings.chunk_size),
+            self.1.div_euclid(settings.chunk_size),
+        )
+    }
+
+    // position inside its chunk, both in `0..chunk_size`
+    pub fn local(self, settings: &TerrainSettings) -> (i32, i32) {
+        (
+            self.0.rem_euclid(settings.chunk_size),
+            self.1.rem_euclid(settings.chunk_size),
+        )
+    }
+
+    // corner with the lowest x and z, this is where the height function is sampled
+    pub fn corner(self, settings: &TerrainSettings) -> WorldPos {
+        let half_chunk = settings.chunk_size as f32 / 2.;
+        WorldPos::new(
+            (self.0 as f32 - half_chunk) * settings.voxel_size,
+            (self.1 as f32 - half_chunk) * settings.voxel_size,
+        )
+    }
+
+    // use this rather than `corner` to round trip through world space without rounding issues
+    pub fn center(self, settings: &TerrainSettings) -> WorldPos {
+        let corner = self.corner(settings);
+        let half_voxel = settings.voxel_size / 2.;
+        WorldPos::new(corner.x + half_voxel, corner.z + half_voxel)
+    }
+
+    pub fn offset(self, dx: i32, dy: i32) -> Self {
+        VoxelCoord(self.0 + dx, self.1 + dy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // even and odd chunk sizes, with voxel sizes that aren't exact in binary
+    fn all_settings() -> Vec<TerrainSettings> {
+        vec![(50, 30.), (5, 0.7), (8, 2.), (3, 1.3)]
+            .into_iter()
+            .map(|(chunk_size, voxel_size)| TerrainSettings {
+                chunk_size,
+                voxel_size,
+                ..TerrainSettings::default()
+            })
+            .collect()
+    }
+
+    fn chunks() -> impl Iterator<Item = ChunkCoord> {
+        (-6..=6).flat_map(|x| (-6..=6).map(move |y| ChunkCoord(x, y)))
+    }
+
+    #[test]
+    fn chunk_center_round_trips() {
+        for settings in all_settings() {
+            for chunk in chunks() {
+                let center = chunk.center(&settings);
+                assert_eq!(ChunkCoord::from_world(center, &settings), chunk);
+            }
+        }
+    }
+
+    #[test]
+    fn voxel_center_round_trips() {
+        for settings in all_settings() {
+            let range = -3 * settings.chunk_size..3 * settings.chunk_size;
+            for x in range.clone() {
+                for y in range.clone() {
+                    let voxel = VoxelCoord(x, y);
+                    let center = voxel.center(&settings);
+                    assert_eq!(VoxelCoord::from_world(center, &settings), voxel);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn world_position_lies_in_its_voxel() {
+        for settings in all_settings() {
+            let extent = 2.5 * settings.chunk_length();
+            let steps = 97; // not a multiple of the chunk size, so positions land all over voxels
+            for i in 0..=steps {
+                for j in 0..=steps {
+                    let pos = WorldPos::new(
+                        -extent + 2. * extent * i as f32 / steps as f32,
+                        -extent + 2. * extent * j as f32 / steps as f32,
+                    );
+                    let voxel = VoxelCoord::from_world(pos, &settings);
+                    let center = voxel.center(&settings);
+                    let half = settings.voxel_size / 2. + 1e-3;
+                    assert!(
+                        (pos.x - center.x).abs() <= half && (pos.z - center.z).abs() <= half,
+                        "{:?} is not in {:?}",
+                        pos,
+                        voxel
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn local_voxels_round_trip() {
+        for settings in all_settings() {
+            for chunk in chunks() {
+                assert_eq!(chunk.origin_voxel(&settings).chunk(&settings), chunk);
+                for x in 0..settings.chunk_size {
+                    for y in 0..settings.chunk_size {
+                        let voxel = VoxelCoord::from_local(chunk, x, y, &settings);
+                        assert_eq!(voxel.chunk(&settings), chunk);
+                        assert_eq!(voxel.local(&settings), (x, y));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn chunk_center_is_inside_chunk() {
+        for settings in all_settings() {
+            for chunk in chunks() {
+                let center = chunk.center(&settings);
+                let first = chunk.origin_voxel(&settings).corner(&settings);
+                let length = settings.chunk_length();
+                assert!((center.x - (first.x + length / 2.)).abs() < 1e-2);
+                assert!((center.z - (first.z + length / 2.)).abs() < 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn voxel_steps_cross_chunk_borders() {
+        for settings in all_settings() {
+            for chunk in chunks() {
+                let last = VoxelCoord::from_local(
+                    chunk,
+                    settings.chunk_size - 1,
+                    settings.chunk_size - 1,
+                    &settings,
+                );
+                let next = last.offset(1, 1);
+                assert_eq!(next.chunk(&settings), chunk.offset(1, 1));
+                assert_eq!(next.local(&settings), (0, 0));
+
+                let previous = chunk.origin_voxel(&settings).offset(-1, 0);
+                assert_eq!(previous.chunk(&settings), chunk.offset(-1, 0));
+                assert_eq!(previous.local(&settings), (settings.chunk_size - 1, 0));
+            }
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use noise::{NoiseFn, Perlin, Seedable};
+use serde::{Deserialize, Serialize};
+
+// a post-processing step applied to generated heights
+// every pass works in world coordinates so that neighbouring chunks agree on shared voxels,
+// as long as the grid is generated with enough overlap (see `HeightPass::margin`)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum HeightPass {
+    // carves river beds where a low frequency noise crosses zero
+    Rivers {
+        frequency: f32, // noise frequency in world units
+        width: f32,     // noise band considered part of the river (0..1)
+        depth: f32,     // depth of the river bed at its center
+    },
+    // moves material down slopes steeper than `talus` (rise over run)
+    Thermal {
+        iterations: usize,
+        talus: f32,
+        strength: f32,
+    },
+    // simulates rain droplets eroding and depositing sediment
+    Hydraulic {
+        density: f32,    // chance for a droplet to spawn on a voxel
+        lifetime: usize, // max steps a droplet travels, each step is at most 1 voxel
+        inertia: f32,
+        capacity: f32,
+        erosion: f32,
+        deposition: f32,
+        evaporation: f32,
+        gravity: f32,
+    },
+}
+
+impl HeightPass {
+    // number of voxels around a region this pass needs to produce consistent results inside it
+    pub fn margin(&self) -> usize {
+        match self {
+            HeightPass::Rivers { .. } => 0,
+            HeightPass::Thermal { iterations, .. } => *iterations,
+            // a droplet moves at most 1 voxel per step and touches the cells around it
+            HeightPass::Hydraulic { lifetime, .. } => *lifetime + 2,
+        }
+    }
+}
+
+// a square patch of heights in world space
+pub struct HeightGrid {
+    pub origin: (f32, f32), // world position of cell (0, 0)
+    pub size: usize,        // cells per side
+    pub cell_size: f32,
+    pub values: Vec<f32>,
+}
+
+impl HeightGrid {
+    pub fn generate<F>(origin: (f32, f32), size: usize, cell_size: f32, height: F) -> Self
+    where
+        F: Fn(f32, f32) -> f32,
+    {
+        let values = (0..size * size)
+            .map(|i| {
+                let x = origin.0 + (i % size) as f32 * cell_size;
+                let y = origin.1 + (i / size) as f32 * cell_size;
+                height(x, y)
+            })
+            .collect();
+
+        Self {
+            origin,
+            size,
+            cell_size,
+            values,
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> f32 {
+        self.values[y * self.size + x]
+    }
+
+    pub fn world_pos(&self, x: usize, y: usize) -> (f32, f32) {
+        (
+            self.origin.0 + x as f32 * self.cell_size,
+            self.origin.1 + y as f32 * self.cell_size,
+        )
+    }
+
+    // global (world) index of a cell, stable across chunks
+    fn world_cell(&self, x: usize, y: usize) -> (i32, i32) {
+        let (wx, wy) = self.world_pos(x, y);
+        (
+            (wx / self.cell_size).round() as i32,
+            (wy / self.cell_size).round() as i32,
+        )
+    }
+
+    // bilinear height and gradient at `(u, v)` inside cell `(cx, cy)`
+    fn sample(&self, (cx, cy): (usize, usize), (u, v): (f32, f32)) -> (f32, f32, f32) {
+        let h00 = self.get(cx, cy);
+        let h10 = self.get(cx + 1, cy);
+        let h01 = self.get(cx, cy + 1);
+        let h11 = self.get(cx + 1, cy + 1);
+
+        let grad_x = (h10 - h00) * (1. - v) + (h11 - h01) * v;
+        let grad_y = (h01 - h00) * (1. - u) + (h11 - h10) * u;
+        let height =
+            h00 * (1. - u) * (1. - v) + h10 * u * (1. - v) + h01 * (1. - u) * v + h11 * u * v;
+
+        (height, grad_x, grad_y)
+    }
+}
+
+// a droplet position kept relative to its spawn cell, so a droplet does the same float math in
+// every grid it is simulated in no matter where the grid starts
+#[derive(Clone, Copy)]
+struct DropletPos {
+    cell: (usize, usize),
+    offset: (f32, f32),
+}
+
+impl DropletPos {
+    // the cell the droplet is in and its position inside it, `None` once the cell and its
+    // neighbours to the right and below aren't all in a grid with `size` cells per side
+    fn locate(self, size: usize) -> Option<((usize, usize), (f32, f32))> {
+        let fx = self.offset.0.floor();
+        let fy = self.offset.1.floor();
+        let cx = self.cell.0 as isize + fx as isize;
+        let cy = self.cell.1 as isize + fy as isize;
+        if cx < 0 || cy < 0 || cx + 1 >= size as isize || cy + 1 >= size as isize {
+            return None;
+        }
+        Some((
+            (cx as usize, cy as usize),
+            (self.offset.0 - fx, self.offset.1 - fy),
+        ))
+    }
+}
+
+// spreads `amount` over the 4 cells around `(u, v)` inside cell `(cx, cy)` of a grid with `size`
+// cells per side
+fn add_bilinear(
+    values: &mut [f32],
+    size: usize,
+    (cx, cy): (usize, usize),
+    (u, v): (f32, f32),
+    amount: f32,
+) {
+    values[cy * size + cx] += amount * (1. - u) * (1. - v);
+    values[cy * size + cx + 1] += amount * u * (1. - v);
+    values[(cy + 1) * size + cx] += amount * (1. - u) * v;
+    values[(cy + 1) * size + cx + 1] += amount * u * v;
+}
+
+pub fn pipeline_margin(passes: &[HeightPass]) -> usize {
+    passes.iter().map(HeightPass::margin).sum()
+}
+
+pub fn apply_passes(grid: &mut HeightGrid, passes: &[HeightPass], seed: u32) {
+    for pass in passes {
+        match pass {
+            HeightPass::Rivers {
+                frequency,
+                width,
+                depth,
+            } => carve_rivers(grid, seed, *frequency, *width, *depth),
+            HeightPass::Thermal {
+                iterations,
+                talus,
+                strength,
+            } => thermal_erosion(grid, *iterations, *talus, *strength),
+            HeightPass::Hydraulic { .. } => hydraulic_erosion(grid, pass, seed),
+        }
+    }
+}
+
+fn carve_rivers(grid: &mut HeightGrid, seed: u32, frequency: f32, width: f32, depth: f32) {
+    let noise = Perlin::new().set_seed(seed.wrapping_add(1));
+
+    for y in 0..grid.size {
+        for x in 0..grid.size {
+            let (wx, wy) = grid.world_pos(x, y);
+            let n = noise
+                .get([(wx * frequency) as f64, (wy * frequency) as f64])
+                .abs() as f32;
+            if n < width {
+                let t = 1. - n / width;
+                grid.values[y * grid.size + x] -= depth * t * t;
+            }
+        }
+    }
+}
+
+fn thermal_erosion(grid: &mut HeightGrid, iterations: usize, talus: f32, strength: f32) {
+    let size = grid.size;
+    let threshold = talus * grid.cell_size;
+
+    for _ in 0..iterations {
+        let mut delta = vec![0.; grid.values.len()];
+
+        // each pair of neighbours is visited once so the result doesn't depend on iteration order
+        for y in 0..size {
+            for x in 0..size {
+                let i = y * size + x;
+                let neighbours = [(x + 1, y), (x, y + 1)];
+                for &(nx, ny) in neighbours.iter() {
+                    if nx >= size || ny >= size {
+                        continue;
+                    }
+                    let n = ny * size + nx;
+                    let diff = grid.values[i] - grid.values[n];
+                    if diff.abs() > threshold {
+                        // a cell can lose material to up to 4 neighbours, keep each share small
+                        let moved = strength * (diff.abs() - threshold) * 0.25 * diff.signum();
+                        delta[i] -= moved;
+                        delta[n] += moved;
+                    }
+                }
+            }
+        }
+
+        for (value, d) in grid.values.iter_mut().zip(delta.into_iter()) {
+            *value += d;
+        }
+    }
+}
+
+fn hydraulic_erosion(grid: &mut HeightGrid, pass: &HeightPass, seed: u32) {
+    let (density, lifetime, inertia, capacity, erosion, deposition, evaporation, gravity) =
+        match *pass {
+            HeightPass::Hydraulic {
+                density,
+                lifetime,
+                inertia,
+                capacity,
+                erosion,
+                deposition,
+                evaporation,
+                gravity,
+            } => (
+                density,
+                lifetime,
+                inertia,
+                capacity,
+                erosion,
+                deposition,
+                evaporation,
+                gravity,
+            ),
+            _ => return,
+        };
+
+    // every droplet runs on the heights from before the pass, so its path only depends on the
+    // cells near it and not on which droplets ran earlier in this grid. droplets are spawned from
+    // world cells in row-major order and their changes are summed per cell in that order, which
+    // gives overlapping grids the same result away from their edges (see `HeightPass::margin`)
+    let mut delta = vec![0.; grid.values.len()];
+    for cy in 0..grid.size - 1 {
+        for cx in 0..grid.size - 1 {
+            let (wx, wy) = grid.world_cell(cx, cy);
+            let roll = hash(seed, wx, wy);
+            if unit(roll) >= density {
+                continue;
+            }
+
+            let mut pos = DropletPos {
+                cell: (cx, cy),
+                offset: (
+                    unit(hash(roll, wx, wy)).min(0.999),
+                    unit(hash(roll, wy, wx)).min(0.999),
+                ),
+            };
+            let (mut dir_x, mut dir_y) = (0., 0.);
+            let mut speed = 1.;
+            let mut water = 1.;
+            let mut sediment = 0.;
+
+            for _ in 0..lifetime {
+                // droplets need a full cell around them for bilinear sampling
+                let (old_cell, old_uv) = match pos.locate(grid.size) {
+                    Some(located) => located,
+                    None => break,
+                };
+                let (height, grad_x, grad_y) = grid.sample(old_cell, old_uv);
+
+                dir_x = dir_x * inertia - grad_x * (1. - inertia);
+                dir_y = dir_y * inertia - grad_y * (1. - inertia);
+                let len = (dir_x * dir_x + dir_y * dir_y).sqrt();
+                if len <= std::f32::EPSILON {
+                    break;
+                }
+                dir_x /= len;
+                dir_y /= len;
+
+                pos.offset.0 += dir_x;
+                pos.offset.1 += dir_y;
+                let (new_height, _, _) = match pos.locate(grid.size) {
+                    Some((cell, uv)) => grid.sample(cell, uv),
+                    None => break,
+                };
+                let height_diff = new_height - height;
+                let max_sediment = (-height_diff).max(0.01) * speed * water * capacity;
+
+                if sediment > max_sediment || height_diff > 0. {
+                    // going uphill fills the pit behind, otherwise drop the excess
+                    let amount = if height_diff > 0. {
+                        height_diff.min(sediment)
+                    } else {
+                        (sediment - max_sediment) * deposition
+                    };
+                    sediment -= amount;
+                    add_bilinear(&mut delta, grid.size, old_cell, old_uv, amount);
+                } else {
+                    let amount = ((max_sediment - sediment) * erosion).min(-height_diff);
+                    sediment += amount;
+                    add_bilinear(&mut delta, grid.size, old_cell, old_uv, -amount);
+                }
+
+                speed = (speed * speed - height_diff * gravity).max(0.).sqrt();
+                water *= 1. - evaporation;
+            }
+        }
+    }
+
+    for (value, d) in grid.values.iter_mut().zip(delta.into_iter()) {
+        *value += d;
+    }
+}
+
+fn hash(seed: u32, x: i32, y: i32) -> u32 {
+    let mut h = seed
+        .wrapping_mul(0x9E37_79B9)
+        .wrapping_add((x as u32).wrapping_mul(0x85EB_CA6B))
+        .wrapping_add((y as u32).wrapping_mul(0xC2B2_AE35));
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^= h >> 16;
+    h
+}
+
+fn unit(value: u32) -> f32 {
+    value as f32 / std::u32::MAX as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passes() -> Vec<HeightPass> {
+        vec![
+            HeightPass::Rivers {
+                frequency: 0.05,
+                width: 0.1,
+                depth: 4.,
+            },
+            HeightPass::Thermal {
+                iterations: 3,
+                talus: 0.5,
+                strength: 0.5,
+            },
+            HeightPass::Hydraulic {
+                density: 0.5,
+                lifetime: 10,
+                inertia: 0.1,
+                capacity: 4.,
+                erosion: 0.3,
+                deposition: 0.3,
+                evaporation: 0.05,
+                gravity: 4.,
+            },
+        ]
+    }
+
+    fn height(x: f32, y: f32) -> f32 {
+        let noise = Perlin::new().set_seed(7);
+        noise.get([(x * 0.08) as f64, (y * 0.08) as f64]) as f32 * 20. + x * 0.3
+    }
+
+    #[test]
+    fn overlapping_grids_agree_on_shared_cells() {
+        let passes = passes();
+        let margin = pipeline_margin(&passes) + 1;
+        let size = 2 * margin + 24;
+        // the second grid starts 16 cells right and 9 cells down of the first
+        let offset = (16, 9);
+
+        let mut a = HeightGrid::generate((0., 0.), size, 1., height);
+        let mut b = HeightGrid::generate((offset.0 as f32, offset.1 as f32), size, 1., height);
+        apply_passes(&mut a, &passes, 3);
+        apply_passes(&mut b, &passes, 3);
+
+        let mut compared = 0;
+        for y in margin..size - margin {
+            for x in margin..size - margin {
+                let (bx, by) = (x as i32 - offset.0, y as i32 - offset.1);
+                let inside_b = |v: i32| v >= margin as i32 && v < (size - margin) as i32;
+                if !inside_b(bx) || !inside_b(by) {
+                    continue;
+                }
+                assert_eq!(
+                    a.get(x, y),
+                    b.get(bx as usize, by as usize),
+                    "cell ({}, {}) differs",
+                    x,
+                    y
+                );
+                compared += 1;
+            }
+        }
+        assert!(compared > 0);
+    }
+
+    #[test]
+    fn hydraulic_erosion_changes_heights() {
+        let passes = passes();
+        let mut grid = HeightGrid::generate((0., 0.), 32, 1., height);
+        let before = grid.values.clone();
+        apply_passes(&mut grid, &passes[2..], 3);
+        assert_ne!(before, grid.values);
+    }
+}
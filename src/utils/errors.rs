@@ -1,12 +1,189 @@
-use std::io;
+use amethyst::config::ConfigError;
+use std::{
+  error::Error,
+  fmt::{self, Display},
+  io,
+  path::PathBuf,
+};
+
+// errors of this crate, with the file or prefab they are about
+#[derive(Debug)]
+pub enum GameError {
+  Io {
+    path: Option<PathBuf>,
+    source: io::Error,
+  },
+  Ron {
+    path: PathBuf,
+    source: ConfigError,
+  },
+  AssetEnumeration(AssetEnumerationError),
+  MissingPrefab {
+    role: String, // what the prefab is used for, e.g. `level`
+    id: String,
+  },
+  PrefabRegistry(PrefabRegistryError),
+  PrefabCycle {
+    ids: Vec<String>, // the variant chain, ending with the repeated prefab
+  },
+  InvalidConfig {
+    path: PathBuf,
+    reason: String,
+  },
+  Terrain {
+    path: PathBuf,
+    source: HeightmapError,
+  },
+}
+
+impl Display for GameError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      GameError::Io {
+        path: Some(path),
+        source,
+      } => write!(f, "{}: {}", path.display(), source),
+      GameError::Io { path: None, source } => write!(f, "{}", source),
+      GameError::Ron { path, source } => write!(f, "{}: {}", path.display(), source),
+      GameError::AssetEnumeration(e) => write!(f, "{}", e),
+      GameError::MissingPrefab { role, id } => {
+        write!(f, "no prefab named `{}` (used as {})", id, role)
+      }
+      GameError::PrefabRegistry(e) => write!(f, "{}", e),
+      GameError::PrefabCycle { ids } => {
+        write!(f, "prefab variants extend each other: {}", ids.join(" -> "))
+      }
+      GameError::InvalidConfig { path, reason } => write!(f, "{}: {}", path.display(), reason),
+      GameError::Terrain { path, source } => write!(f, "{}: {}", path.display(), source),
+    }
+  }
+}
+
+impl Error for GameError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      GameError::Io { source, .. } => Some(source),
+      GameError::Ron { source, .. } => Some(source),
+      GameError::AssetEnumeration(e) => Some(e),
+      GameError::PrefabRegistry(e) => Some(e),
+      GameError::Terrain { source, .. } => Some(source),
+      GameError::MissingPrefab { .. }
+      | GameError::PrefabCycle { .. }
+      | GameError::InvalidConfig { .. } => None,
+    }
+  }
+}
+
+impl From<AssetEnumerationError> for GameError {
+  fn from(err: AssetEnumerationError) -> GameError {
+    GameError::AssetEnumeration(err)
+  }
+}
+
+impl From<PrefabRegistryError> for GameError {
+  fn from(err: PrefabRegistryError) -> GameError {
+    GameError::PrefabRegistry(err)
+  }
+}
 
 #[derive(Debug)]
 pub enum AssetEnumerationError {
+  Io { path: PathBuf, error: io::Error },
+  InvalidPath(PathBuf), // not valid unicode
+}
+
+impl Display for AssetEnumerationError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      AssetEnumerationError::Io { path, error } => {
+        write!(f, "can't read {}: {}", path.display(), error)
+      }
+      AssetEnumerationError::InvalidPath(path) => {
+        write!(f, "{} is not a valid unicode path", path.display())
+      }
+    }
+  }
+}
+
+impl Error for AssetEnumerationError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      AssetEnumerationError::Io { error, .. } => Some(error),
+      AssetEnumerationError::InvalidPath(_) => None,
+    }
+  }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PrefabRegistryError {
+  DuplicateId(String),
+  DuplicateName {
+    name: String,
+    first: String, // ids of the two prefabs using the name
+    second: String,
+  },
+}
+
+impl Display for PrefabRegistryError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      PrefabRegistryError::DuplicateId(id) => {
+        write!(f, "a prefab with id `{}` is already registered", id)
+      }
+      PrefabRegistryError::DuplicateName {
+        name,
+        first,
+        second,
+      } => write!(
+        f,
+        "prefabs `{}` and `{}` are both named `{}`, lookups by that name use `{}`",
+        first, second, name, first
+      ),
+    }
+  }
+}
+
+impl Error for PrefabRegistryError {}
+
+#[derive(Debug)]
+pub enum HeightmapError {
   Io(io::Error),
+  Image(image::ImageError),
+  Size { expected: usize, actual: usize },
+}
+
+impl Display for HeightmapError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      HeightmapError::Io(e) => write!(f, "{}", e),
+      HeightmapError::Image(e) => write!(f, "{}", e),
+      HeightmapError::Size { expected, actual } => write!(
+        f,
+        "height data has the wrong size, expected {} but found {}",
+        expected, actual
+      ),
+    }
+  }
+}
+
+impl Error for HeightmapError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      HeightmapError::Io(e) => Some(e),
+      HeightmapError::Image(e) => Some(e),
+      HeightmapError::Size { .. } => None,
+    }
+  }
+}
+
+impl From<io::Error> for HeightmapError {
+  fn from(err: io::Error) -> HeightmapError {
+    HeightmapError::Io(err)
+  }
 }
 
-impl From<io::Error> for AssetEnumerationError {
-  fn from(err: io::Error) -> AssetEnumerationError {
-    AssetEnumerationError::Io(err)
+impl From<image::ImageError> for HeightmapError {
+  fn from(err: image::ImageError) -> HeightmapError {
+    HeightmapError::Image(err)
   }
 }
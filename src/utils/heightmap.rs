@@ -0,0 +1,264 @@
+use crate::{
+    resources::terrain::HeightGenerator,
+    systems::terrain::TerrainSettings,
+    utils::{coords::ChunkCoord, errors::HeightmapError},
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, ops::Range, path::Path};
+
+// what happens when sampling outside of the image
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum HeightmapWrap {
+    Tile,
+    Clamp,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum HeightmapFormat {
+    Png,
+    Raw16 { width: usize, height: usize }, // headerless little endian, see `from_raw16`
+}
+
+// a heightmap to use instead of noise, set in `config/terrain.ron`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HeightmapSettings {
+    pub path: String, // relative to the application root
+    pub format: HeightmapFormat,
+    pub wrap: HeightmapWrap,
+    pub scale: f32,  // world units per pixel
+    pub height: f32, // world height of a white pixel (relative to `offset`)
+    pub offset: f32,
+}
+
+impl HeightmapSettings {
+    pub fn load(&self, root: &Path) -> Result<Heightmap, HeightmapError> {
+        let path = root.join(&self.path);
+        let heightmap = match self.format {
+            HeightmapFormat::Png => Heightmap::from_png(path)?,
+            HeightmapFormat::Raw16 { width, height } => Heightmap::from_raw16(path, width, height)?,
+        };
+
+        Ok(heightmap
+            .with_wrap(self.wrap)
+            .with_scale(self.scale)
+            .with_height(self.height, self.offset))
+    }
+}
+
+// a grayscale image used as terrain height source, centered on the world origin
+#[derive(Clone, Debug)]
+pub struct Heightmap {
+    width: usize,
+    height: usize,
+    values: Vec<f32>, // normalized to 0..1
+    wrap: HeightmapWrap,
+    scale: f32,        // world units per pixel
+    height_scale: f32, // world height of a white pixel (relative to `height_offset`)
+    height_offset: f32,
+}
+
+impl Heightmap {
+    // `values` are row by row and need to cover the whole image
+    pub fn new(width: usize, height: usize, values: Vec<f32>) -> Result<Self, HeightmapError> {
+        if values.len() != width * height {
+            return Err(HeightmapError::Size {
+                expected: width * height,
+                actual: values.len(),
+            });
+        }
+        Ok(Self {
+            width,
+            height,
+            values,
+            wrap: HeightmapWrap::Clamp,
+            scale: 1.,
+            height_scale: 1.,
+            height_offset: 0.,
+        })
+    }
+
+    // reads a 16 bit grayscale png at full precision, other images are converted to 8 bit
+    pub fn from_png<P: AsRef<Path>>(path: P) -> Result<Self, HeightmapError> {
+        let path = path.as_ref();
+        if let Some(heightmap) = Self::read_gray16(path)? {
+            return Ok(heightmap);
+        }
+
+        let image = image::open(path)?.to_luma();
+        let (width, height) = image.dimensions();
+        let values = image
+            .into_raw()
+            .into_iter()
+            .map(|v| v as f32 / std::u8::MAX as f32)
+            .collect();
+
+        Self::new(width as usize, height as usize, values)
+    }
+
+    // `None` if the file isn't a 16 bit grayscale png, image only decodes those to 8 bit
+    fn read_gray16(path: &Path) -> Result<Option<Self>, HeightmapError> {
+        let mut decoder = png::Decoder::new(fs::File::open(path)?);
+        decoder.set_transformations(png::Transformations::IDENTITY);
+        let (info, mut reader) = match decoder.read_info() {
+            Ok(decoded) => decoded,
+            Err(_) => return Ok(None),
+        };
+        if info.color_type != png::ColorType::Grayscale || info.bit_depth != png::BitDepth::Sixteen
+        {
+            return Ok(None);
+        }
+
+        let mut bytes = vec![0; info.buffer_size()];
+        reader
+            .next_frame(&mut bytes)
+            .map_err(image::ImageError::from)?;
+        // png stores samples big endian
+        let values = bytes
+            .chunks(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as f32 / std::u16::MAX as f32)
+            .collect();
+
+        Self::new(info.width as usize, info.height as usize, values).map(Some)
+    }
+
+    // reads a headerless little endian 16 bit heightmap
+    pub fn from_raw16<P: AsRef<Path>>(
+        path: P,
+        width: usize,
+        height: usize,
+    ) -> Result<Self, HeightmapError> {
+        let bytes = fs::read(path)?;
+        if bytes.len() != width * height * 2 {
+            return Err(HeightmapError::Size {
+                expected: width * height * 2,
+                actual: bytes.len(),
+            });
+        }
+        let values = bytes
+            .chunks(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]) as f32 / std::u16::MAX as f32)
+            .collect();
+
+        Self::new(width, height, values)
+    }
+
+    pub fn with_wrap(mut self, wrap: HeightmapWrap) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_height(mut self, height_scale: f32, height_offset: f32) -> Self {
+        self.height_scale = height_scale;
+        self.height_offset = height_offset;
+        self
+    }
+
+    // bilinear height at world position (x, y)
+    pub fn get_height(&self, x: f32, y: f32) -> f32 {
+        let px = x / self.scale + self.width as f32 / 2.;
+        let py = y / self.scale + self.height as f32 / 2.;
+        let ix = px.floor();
+        let iy = py.floor();
+        let u = px - ix;
+        let v = py - iy;
+        let (ix, iy) = (ix as i64, iy as i64);
+
+        let value = self.pixel(ix, iy) * (1. - u) * (1. - v)
+            + self.pixel(ix + 1, iy) * u * (1. - v)
+            + self.pixel(ix, iy + 1) * (1. - u) * v
+            + self.pixel(ix + 1, iy + 1) * u * v;
+
+        value * self.height_scale + self.height_offset
+    }
+
+    fn pixel(&self, x: i64, y: i64) -> f32 {
+        let (w, h) = (self.width as i64, self.height as i64);
+        let (x, y) = match self.wrap {
+            HeightmapWrap::Tile => (x.rem_euclid(w), y.rem_euclid(h)),
+            HeightmapWrap::Clamp => (x.max(0).min(w - 1), y.max(0).min(h - 1)),
+        };
+        self.values[(y * w + x) as usize]
+    }
+}
+
+// heights of a region of chunks, exactly as the voxel generator produces them
+// one sample per voxel, rows go along +z; returns (width, height, values)
+pub fn render_heights(
+    settings: &TerrainSettings,
+    generator: &HeightGenerator,
+    chunks_x: Range<i32>,
+    chunks_y: Range<i32>,
+) -> (usize, usize, Vec<f32>) {
+    let chunk_size = settings.chunk_size as usize;
+    let width = chunks_x.len() * chunk_size;
+    let height = chunks_y.len() * chunk_size;
+    let mut values = vec![0.; width * height];
+
+    for (row, cy) in chunks_y.enumerate() {
+        for (column, cx) in chunks_x.clone().enumerate() {
+            let (grid, margin) = generator.chunk_heights(settings, ChunkCoord(cx, cy));
+            let margin = margin as usize;
+
+            for y in 0..chunk_size {
+                for x in 0..chunk_size {
+                    let index = (row * chunk_size + y) * width + column * chunk_size + x;
+                    values[index] = grid.get(x + margin, y + margin);
+                }
+            }
+        }
+    }
+
+    (width, height, values)
+}
+
+// writes heights from `render_heights` to an 8 bit grayscale png, normalized to their range
+pub fn write_png<P: AsRef<Path>>(
+    path: P,
+    width: usize,
+    height: usize,
+    values: &[f32],
+) -> Result<(), HeightmapError> {
+    let pixels = normalize(values)
+        .map(|v| (v * std::u8::MAX as f32) as u8)
+        .collect::<Vec<_>>();
+
+    image::save_buffer(
+        path,
+        &pixels,
+        width as u32,
+        height as u32,
+        image::ColorType::Gray(8),
+    )?;
+    Ok(())
+}
+
+// writes heights from `render_heights` as a headerless little endian 16 bit heightmap,
+// normalized to their range like `write_png`
+pub fn write_raw16<P: AsRef<Path>>(path: P, values: &[f32]) -> Result<(), HeightmapError> {
+    let bytes = normalize(values)
+        .flat_map(|v| {
+            let b = ((v * std::u16::MAX as f32) as u16).to_le_bytes();
+            vec![b[0], b[1]]
+        })
+        .collect::<Vec<_>>();
+
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+fn normalize<'a>(values: &'a [f32]) -> impl Iterator<Item = f32> + 'a {
+    let min = values.iter().cloned().fold(std::f32::INFINITY, f32::min);
+    let max = values
+        .iter()
+        .cloned()
+        .fold(std::f32::NEG_INFINITY, f32::max);
+    let range = (max - min).max(std::f32::EPSILON);
+    log::info!("Exporting heights in range {} to {}", min, max);
+
+    values.iter().map(move |v| (v - min) / range)
+}
@@ -0,0 +1,132 @@
+use crate::utils::{
+    assets::{enumerate_assets, log_failed_entries},
+    errors::GameError,
+};
+use amethyst::{config::Config, utils::application_root_dir};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_PATH: &str = "assets/manifest.ron";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum AssetKind {
+    Ui,
+    Critter,
+    Level,
+    Prop,
+}
+
+impl AssetKind {
+    // directory scanned for this kind when there is no manifest
+    pub fn directory(self) -> &'static str {
+        match self {
+            AssetKind::Ui => "prefabs/ui",
+            AssetKind::Critter => "prefabs/critters",
+            AssetKind::Level => "prefabs/levels",
+            AssetKind::Prop => "prefabs/props",
+        }
+    }
+
+    pub fn all() -> [AssetKind; 4] {
+        [
+            AssetKind::Ui,
+            AssetKind::Critter,
+            AssetKind::Level,
+            AssetKind::Prop,
+        ]
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ManifestEntry {
+    pub id: String,
+    pub kind: AssetKind,
+    pub path: String, // relative to the assets folder
+}
+
+// every asset the game loads up front, see `assets/manifest.ron`
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct AssetManifest {
+    pub assets: Vec<ManifestEntry>,
+}
+
+impl AssetManifest {
+    // listed files aren't checked here, a missing one fails its own load
+    // and is reported with the other loading errors
+    pub fn load(path: &Path) -> Result<Self, GameError> {
+        Self::load_no_fallback(path).map_err(|source| GameError::Ron {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    // the manifest next to the executable, in debug builds a missing or broken manifest falls
+    // back to scanning the asset directories so new files show up without regenerating it
+    pub fn load_default() -> Result<Self, GameError> {
+        let path = root_dir()?.join(MANIFEST_PATH);
+        match Self::load(&path) {
+            Ok(manifest) => Ok(manifest),
+            Err(e) if cfg!(debug_assertions) => {
+                log::warn!(
+                    "Could not load the asset manifest ({}), scanning asset directories",
+                    e
+                );
+                Self::scan()
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // builds a manifest from the asset directories, ids are the file stems
+    pub fn scan() -> Result<Self, GameError> {
+        let mut assets = Vec::new();
+        for &kind in AssetKind::all().iter() {
+            let entries =
+                enumerate_assets(kind.directory(), &["ron"])?.filter_map(log_failed_entries);
+            for (file_name, path) in entries {
+                let id = Path::new(&file_name)
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .unwrap_or(&file_name)
+                    .to_owned();
+                assets.push(ManifestEntry {
+                    id,
+                    kind,
+                    path: path.replace('\\', "/"),
+                });
+            }
+        }
+        Ok(Self { assets })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GameError> {
+        self.write(path).map_err(|source| GameError::Ron {
+            path: path.to_owned(),
+            source,
+        })
+    }
+
+    pub fn entries(&self, kind: AssetKind) -> impl Iterator<Item = &ManifestEntry> {
+        self.assets.iter().filter(move |entry| entry.kind == kind)
+    }
+}
+
+// regenerates the manifest from the asset directories
+pub fn generate_manifest(out: Option<PathBuf>) -> Result<(), GameError> {
+    let out = match out {
+        Some(out) => out,
+        None => root_dir()?.join(MANIFEST_PATH),
+    };
+    let manifest = AssetManifest::scan()?;
+    log::info!("Writing {} assets to {:?}", manifest.assets.len(), out);
+    manifest.save(&out)
+}
+
+// the folder manifest paths are relative to
+pub fn assets_dir() -> Result<PathBuf, GameError> {
+    Ok(root_dir()?.join("assets"))
+}
+
+fn root_dir() -> Result<PathBuf, GameError> {
+    application_root_dir().map_err(|source| GameError::Io { path: None, source })
+}
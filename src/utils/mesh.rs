@@ -1,9 +1,12 @@
-use crate::components::terrain::{Chunk, VoxelData};
+use crate::{
+    components::terrain::{Chunk, VoxelData},
+    utils::scatter::Placement,
+};
 use amethyst::{
     core::math::*,
     renderer::rendy::mesh::Indices,
     renderer::{
-        rendy::mesh::{MeshBuilder, Normal, Position, TexCoord},
+        rendy::mesh::{MeshBuilder, Normal, Position, Tangent, TexCoord},
         types::MeshData,
     },
 };
@@ -128,11 +131,13 @@ pub fn create_voxel_mesh2(
         .collect::<Vec<_>>();
 
     let normals = calculate_normals(&vertices, &indices);
+    let tangents = calculate_tangents(&vertices, &normals, &tex_coords, &indices);
 
     MeshData(
         MeshBuilder::new()
             .with_vertices(vertices)
             .with_vertices(normals)
+            .with_vertices(tangents)
             .with_vertices(tex_coords)
             .with_indices(Indices::U16(indices.into())),
     )
@@ -234,21 +239,75 @@ pub fn create_biome_mesh(size: f32) -> MeshData {
         Position([size, 0.0, -size]),
     ];
     let indices = vec![0, 2, 1, 0, 3, 2 as u16];
+    let tex_coords = vec![
+        TexCoord([1.0, 0.0]),
+        TexCoord([0.0, 0.0]),
+        TexCoord([0.0, 1.0]),
+        TexCoord([1.0, 1.0]),
+    ];
     let normals = calculate_normals(&vertices, &indices);
+    let tangents = calculate_tangents(&vertices, &normals, &tex_coords, &indices);
 
     MeshData(
         MeshBuilder::new()
             .with_vertices(vertices)
             .with_vertices(normals)
-            .with_vertices(vec![
-                TexCoord([1.0, 0.0]),
-                TexCoord([0.0, 0.0]),
-                TexCoord([0.0, 1.0]),
-                TexCoord([1.0, 1.0]),
-            ])
+            .with_vertices(tangents)
+            .with_vertices(tex_coords)
             .with_indices(Indices::U16(indices.into())),
     )
 }
+
+// copies of a (non-indexed) shape merged into a single mesh, one per placement
+pub fn create_batch_mesh(
+    base: &(Vec<Position>, Vec<Normal>, Vec<TexCoord>),
+    placements: &[Placement],
+) -> MeshData {
+    let (positions, normals, tex_coords) = base;
+    // the shape is small, so its tangents are computed once and rotated like the normals
+    let indices = (0..positions.len() as u16).collect::<Vec<_>>();
+    let tangents = calculate_tangents(positions, normals, tex_coords, &indices);
+
+    let mut merged_positions = Vec::with_capacity(positions.len() * placements.len());
+    let mut merged_normals = Vec::with_capacity(normals.len() * placements.len());
+    let mut merged_tangents = Vec::with_capacity(tangents.len() * placements.len());
+    let mut merged_tex_coords = Vec::with_capacity(tex_coords.len() * placements.len());
+
+    for placement in placements {
+        let matrix = placement.matrix();
+        let rotation = placement.rotation();
+
+        merged_positions.extend(positions.iter().map(|p| {
+            let p = matrix.transform_point(&Point3::from(p.0));
+            Position([p.x, p.y, p.z])
+        }));
+        merged_normals.extend(
+            normals
+                .iter()
+                .map(|n| Normal((rotation * Vector3::from(n.0)).into())),
+        );
+        merged_tangents.extend(tangents.iter().map(|t| {
+            let [x, y, z, handedness] = t.0;
+            let rotated = rotation * Vector3::new(x, y, z);
+            Tangent([rotated.x, rotated.y, rotated.z, handedness])
+        }));
+        merged_tex_coords.extend(tex_coords.iter().cloned());
+    }
+
+    MeshData(
+        MeshBuilder::new()
+            .with_vertices(merged_positions)
+            .with_vertices(merged_normals)
+            .with_vertices(merged_tangents)
+            .with_vertices(merged_tex_coords),
+    )
+}
+
+// flat quad facing up, meant to sit at sea level over a chunk
+pub fn create_water_mesh(size: f32) -> MeshData {
+    create_biome_mesh(size)
+}
+
 pub fn calculate_normals(vertices: &[Position], indices: &[u16]) -> Vec<Normal> {
     let mut normals = vec![zero::<Vector3<f32>>(); vertices.len()];
     let num_faces = indices.len() / 3;
@@ -271,3 +330,163 @@ pub fn calculate_normals(vertices: &[Position], indices: &[u16]) -> Vec<Normal>
         .map(|n| Normal(n.normalize().into()))
         .collect::<Vec<_>>()
 }
+
+// computes per-vertex tangents (mikktspace style) from positions, normals and uvs
+// the w component stores the handedness of the bitangent so the shader can rebuild it
+pub fn calculate_tangents(
+    vertices: &[Position],
+    normals: &[Normal],
+    tex_coords: &[TexCoord],
+    indices: &[u16],
+) -> Vec<Tangent> {
+    let mut tangents = vec![zero::<Vector3<f32>>(); vertices.len()];
+    let mut bitangents = vec![zero::<Vector3<f32>>(); vertices.len()];
+    let num_faces = indices.len() / 3;
+
+    for face in 0..num_faces {
+        let i0 = indices[face * 3] as usize;
+        let i1 = indices[face * 3 + 1] as usize;
+        let i2 = indices[face * 3 + 2] as usize;
+
+        let a = Vector3::from(vertices[i0].0);
+        let b = Vector3::from(vertices[i1].0);
+        let c = Vector3::from(vertices[i2].0);
+        let uv_a = Vector2::from(tex_coords[i0].0);
+        let uv_b = Vector2::from(tex_coords[i1].0);
+        let uv_c = Vector2::from(tex_coords[i2].0);
+
+        let edge1 = b - a;
+        let edge2 = c - a;
+        let duv1 = uv_b - uv_a;
+        let duv2 = uv_c - uv_a;
+
+        let det = duv1.x * duv2.y - duv2.x * duv1.y;
+        if det.abs() <= std::f32::EPSILON {
+            // degenerate uv mapping, leave it to the fallback below
+            continue;
+        }
+        let r = 1. / det;
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) * r;
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) * r;
+
+        for &i in &[i0, i1, i2] {
+            tangents[i] += tangent;
+            bitangents[i] += bitangent;
+        }
+    }
+
+    tangents
+        .into_iter()
+        .zip(bitangents.into_iter())
+        .zip(normals.iter())
+        .map(|((t, b), n)| {
+            let n = Vector3::from(n.0);
+            // gram-schmidt orthogonalize against the normal
+            let mut tangent = t - n * n.dot(&t);
+            if tangent.norm_squared() <= std::f32::EPSILON {
+                tangent = any_perpendicular(&n);
+            }
+            let tangent = tangent.normalize();
+            let handedness = if n.cross(&tangent).dot(&b) < 0. {
+                -1.
+            } else {
+                1.
+            };
+            Tangent([tangent.x, tangent.y, tangent.z, handedness])
+        })
+        .collect::<Vec<_>>()
+}
+
+fn any_perpendicular(n: &Vector3<f32>) -> Vector3<f32> {
+    let axis = if n.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    n.cross(&axis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // unit quad in the xz plane facing up, u runs along x and v along z
+    fn quad() -> (Vec<Position>, Vec<TexCoord>, Vec<u16>) {
+        let vertices = vec![
+            Position([0.0, 0.0, 0.0]),
+            Position([1.0, 0.0, 0.0]),
+            Position([1.0, 0.0, 1.0]),
+            Position([0.0, 0.0, 1.0]),
+        ];
+        let tex_coords = vec![
+            TexCoord([0.0, 0.0]),
+            TexCoord([1.0, 0.0]),
+            TexCoord([1.0, 1.0]),
+            TexCoord([0.0, 1.0]),
+        ];
+        (vertices, tex_coords, vec![0, 2, 1, 0, 3, 2])
+    }
+
+    fn assert_close(actual: Vector3<f32>, expected: Vector3<f32>) {
+        assert!(
+            (actual - expected).norm() < 1e-5,
+            "expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    // the tangent follows +u and the bitangent rebuilt by the shader follows +v
+    fn assert_tangent_frame(
+        vertices: &[Position],
+        tex_coords: &[TexCoord],
+        indices: &[u16],
+        u: Vector3<f32>,
+        v: Vector3<f32>,
+    ) {
+        let normals = calculate_normals(vertices, indices);
+        let tangents = calculate_tangents(vertices, &normals, tex_coords, indices);
+        assert_eq!(tangents.len(), vertices.len());
+
+        for (tangent, normal) in tangents.iter().zip(normals.iter()) {
+            let [x, y, z, handedness] = tangent.0;
+            let tangent = Vector3::new(x, y, z);
+            let bitangent = Vector3::from(normal.0).cross(&tangent) * handedness;
+            assert_close(tangent, u);
+            assert_close(bitangent, v);
+        }
+    }
+
+    #[test]
+    fn tangents_of_axis_aligned_quad() {
+        let (vertices, tex_coords, indices) = quad();
+        let normals = calculate_normals(&vertices, &indices);
+        for normal in normals.iter() {
+            assert_close(Vector3::from(normal.0), Vector3::y());
+        }
+
+        assert_tangent_frame(&vertices, &tex_coords, &indices, Vector3::x(), Vector3::z());
+    }
+
+    #[test]
+    fn tangents_of_rotated_quad() {
+        let (vertices, tex_coords, indices) = quad();
+        let rotation =
+            Rotation3::from_axis_angle(&Unit::new_normalize(Vector3::new(1.0, 2.0, -0.5)), 0.8);
+        let rotated = vertices
+            .iter()
+            .map(|p| {
+                let p = rotation * Point3::from(p.0);
+                Position([p.x, p.y, p.z])
+            })
+            .collect::<Vec<_>>();
+
+        assert_tangent_frame(
+            &rotated,
+            &tex_coords,
+            &indices,
+            rotation * Vector3::x(),
+            rotation * Vector3::z(),
+        );
+    }
+}
@@ -1,5 +1,11 @@
 pub mod assets;
+pub mod coords;
+pub mod erosion;
 pub mod errors;
+pub mod heightmap;
 pub mod hierarchy_util;
+pub mod manifest;
 pub mod mesh;
 pub mod materials;
+pub mod scatter;
+pub mod terrain_preview;
@@ -0,0 +1,133 @@
+use crate::utils::coords::ChunkCoord;
+use amethyst::core::math::{Matrix4, Rotation3, Vector3};
+
+// where and how a single prop is placed
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub translation: Vector3<f32>,
+    pub angle: f32, // rotation around y
+    pub scale: f32,
+}
+
+impl Placement {
+    pub fn rotation(&self) -> Rotation3<f32> {
+        Rotation3::from_axis_angle(&Vector3::y_axis(), self.angle)
+    }
+
+    pub fn matrix(&self) -> Matrix4<f32> {
+        Matrix4::new_translation(&self.translation)
+            * self.rotation().to_homogeneous()
+            * Matrix4::new_scaling(self.scale)
+    }
+}
+
+// small deterministic generator (splitmix64), good enough for placement
+pub struct SeededRng(u64);
+
+impl SeededRng {
+    pub fn new(seed: u64) -> Self {
+        SeededRng(seed)
+    }
+
+    // a generator unique to a chunk, `salt` separates independent streams for the same chunk
+    pub fn for_chunk(seed: u32, coord: ChunkCoord, salt: u32) -> Self {
+        let mut rng = SeededRng::new(
+            ((seed as u64) << 32)
+                ^ (coord.0 as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                ^ (coord.1 as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+                ^ salt as u64,
+        );
+        // the first outputs are poorly mixed for nearby seeds
+        rng.next_u64();
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
+
+// candidates tried around a point before it is retired
+const POISSON_ATTEMPTS: usize = 30;
+
+// poisson disc sampling (bridson) over the square [0, size)^2,
+// no two points are closer than `radius`
+pub fn poisson_disc(rng: &mut SeededRng, size: f32, radius: f32) -> Vec<(f32, f32)> {
+    if size <= 0. || radius <= 0. {
+        return Vec::new();
+    }
+
+    let cell_size = radius / 2f32.sqrt();
+    let cells = (size / cell_size).ceil() as usize;
+    let cell_of = |(x, y): (f32, f32)| {
+        (
+            ((x / cell_size) as usize).min(cells - 1),
+            ((y / cell_size) as usize).min(cells - 1),
+        )
+    };
+
+    let mut grid: Vec<Option<usize>> = vec![None; cells * cells];
+    let mut points = Vec::new();
+    let mut active = Vec::new();
+
+    let first = (rng.range(0., size), rng.range(0., size));
+    let (cx, cy) = cell_of(first);
+    grid[cy * cells + cx] = Some(0);
+    points.push(first);
+    active.push(0);
+
+    while !active.is_empty() {
+        let slot = ((rng.next_f32() * active.len() as f32) as usize).min(active.len() - 1);
+        let (px, py) = points[active[slot]];
+        let mut found = false;
+
+        for _ in 0..POISSON_ATTEMPTS {
+            let angle = rng.range(0., std::f32::consts::PI * 2.);
+            let distance = rng.range(radius, radius * 2.);
+            let candidate = (px + angle.cos() * distance, py + angle.sin() * distance);
+            if candidate.0 < 0. || candidate.1 < 0. || candidate.0 >= size || candidate.1 >= size {
+                continue;
+            }
+
+            // any point closer than `radius` is at most 2 cells away
+            let (cx, cy) = cell_of(candidate);
+            let too_close = (cy.saturating_sub(2)..(cy + 3).min(cells)).any(|y| {
+                (cx.saturating_sub(2)..(cx + 3).min(cells)).any(|x| match grid[y * cells + x] {
+                    Some(i) => {
+                        let (ox, oy) = points[i];
+                        let (dx, dy) = (ox - candidate.0, oy - candidate.1);
+                        dx * dx + dy * dy < radius * radius
+                    }
+                    None => false,
+                })
+            });
+
+            if !too_close {
+                grid[cy * cells + cx] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+                found = true;
+                break;
+            }
+        }
+
+        if !found {
+            active.swap_remove(slot);
+        }
+    }
+
+    points
+}
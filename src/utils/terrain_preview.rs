@@ -0,0 +1,97 @@
+use crate::{
+    resources::terrain::{Biome, HeightGenerator},
+    systems::terrain::TerrainSettings,
+    utils::{errors::HeightmapError, heightmap},
+};
+use amethyst::core::math::Vector3;
+use std::{ops::Range, path::Path};
+
+// renders height, biome and normal maps of a region of chunks next to each other,
+// e.g. `map.png`, `map_biome.png` and `map_normal.png`, and optionally the 16 bit heights
+pub fn render_previews(
+    settings: &TerrainSettings,
+    generator: &HeightGenerator,
+    chunks_x: Range<i32>,
+    chunks_y: Range<i32>,
+    out: &Path,
+    raw: Option<&Path>,
+) -> Result<(), HeightmapError> {
+    // generating runs the whole post processing pipeline, so it is only done once for all maps
+    let (width, height, values) =
+        heightmap::render_heights(settings, generator, chunks_x, chunks_y);
+    heightmap::write_png(out, width, height, &values)?;
+    if let Some(raw) = raw {
+        log::info!("Writing {}x{} raw heights to {:?}", width, height, raw);
+        heightmap::write_raw16(raw, &values)?;
+    }
+
+    let normals = calculate_normals(width, height, &values, settings.voxel_size);
+
+    let biome_pixels = values
+        .iter()
+        .zip(normals.iter())
+        .flat_map(|(&h, n)| biome_color(Biome::classify(h, 1. - n.y, settings)).to_vec())
+        .collect::<Vec<_>>();
+    save_rgb(&sibling(out, "biome"), &biome_pixels, width, height)?;
+
+    // tangent space convention: flat ground is (128, 128, 255)
+    let normal_pixels = normals
+        .iter()
+        .flat_map(|n| {
+            vec![
+                ((n.x * 0.5 + 0.5) * 255.) as u8,
+                ((n.z * 0.5 + 0.5) * 255.) as u8,
+                ((n.y * 0.5 + 0.5) * 255.) as u8,
+            ]
+        })
+        .collect::<Vec<_>>();
+    save_rgb(&sibling(out, "normal"), &normal_pixels, width, height)
+}
+
+fn calculate_normals(
+    width: usize,
+    height: usize,
+    values: &[f32],
+    voxel_size: f32,
+) -> Vec<Vector3<f32>> {
+    let at = |x: usize, y: usize| values[y * width + x];
+
+    (0..width * height)
+        .map(|i| {
+            let x = i % width;
+            let y = i / width;
+            let dx = at((x + 1).min(width - 1), y) - at(x.saturating_sub(1), y);
+            let dy = at(x, (y + 1).min(height - 1)) - at(x, y.saturating_sub(1));
+            Vector3::new(-dx, 2. * voxel_size, -dy).normalize()
+        })
+        .collect()
+}
+
+fn biome_color(biome: Biome) -> [u8; 3] {
+    match biome {
+        Biome::Water => [40, 80, 200],
+        Biome::Beach => [220, 200, 140],
+        Biome::Grass => [60, 160, 60],
+        Biome::Rock => [120, 110, 100],
+        Biome::Snow => [240, 240, 250],
+    }
+}
+
+fn sibling(path: &Path, suffix: &str) -> std::path::PathBuf {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("terrain");
+    path.with_file_name(format!("{}_{}.png", stem, suffix))
+}
+
+fn save_rgb(path: &Path, pixels: &[u8], width: usize, height: usize) -> Result<(), HeightmapError> {
+    image::save_buffer(
+        path,
+        pixels,
+        width as u32,
+        height as u32,
+        image::ColorType::RGB(8),
+    )?;
+    Ok(())
+}